@@ -0,0 +1,6 @@
+use common::{get_tcp_listener, ShutdownSignal};
+
+fn main() {
+    let listener = get_tcp_listener(None);
+    chat::serve(listener, &ShutdownSignal::new());
+}
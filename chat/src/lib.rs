@@ -1,9 +1,11 @@
 extern crate uuid;
 
-use common::{get_tcp_listener, ASCII_NEWLINE, BUFFER_SIZE, THREAD_SLOW_DOWN};
+use common::{
+    spawn_tcp_for_test, ServerHandle, ShutdownSignal, ASCII_NEWLINE, BUFFER_SIZE, THREAD_SLOW_DOWN,
+};
 use std::collections::HashMap;
 use std::io::{ErrorKind, Read, Write};
-use std::net::{Shutdown, TcpStream};
+use std::net::{Shutdown, TcpListener, TcpStream};
 use std::sync::mpsc::{self, Sender};
 use std::thread;
 use uuid::Uuid;
@@ -59,12 +61,11 @@ impl Client {
     }
 }
 
-fn main() {
-    let listener = get_tcp_listener(None);
+pub fn serve(listener: TcpListener, shutdown: &ShutdownSignal) {
     let mut clients: HashMap<Uuid, Client> = HashMap::new();
     let (transmitter, receiver) = mpsc::channel::<Command>();
 
-    loop {
+    while !shutdown.is_triggered() {
         // Accept new connection, and spawn handler.
         if let Ok((stream, _remote_addr)) = listener.accept() {
             eprintln!("Accepting new TCP connection: {stream:?}");
@@ -93,6 +94,10 @@ fn main() {
     }
 }
 
+pub fn spawn_for_test() -> ServerHandle {
+    spawn_tcp_for_test(|listener, shutdown| serve(listener, &shutdown))
+}
+
 fn handle_command(command: Command, clients: &mut HashMap<Uuid, Client>) {
     let broadcast_message = command.get_broadcast();
     match command {
@@ -193,3 +198,14 @@ fn validate_name(line: Vec<u8>) -> Result<String, ()> {
     }
     Err(())
 }
+
+/// Name validation, for the `chat_name` fuzz target.
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing {
+    /// Any name that is accepted must be non-empty and entirely alphanumeric.
+    pub fn validate_name(data: &[u8]) {
+        if let Ok(name) = super::validate_name(data.to_vec()) {
+            assert!(!name.is_empty() && name.chars().all(char::is_alphanumeric));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Integration tests for Budget Chat.
+
+#[cfg(test)]
+mod test {
+    use chat::spawn_for_test;
+    use std::io::{ErrorKind, Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use testing::{
+        assert_client_receives_bytes, assert_snapshot,
+        conformance::{assert_conforms, Problem},
+        connect,
+        snapshot::{Conversation, Format},
+    };
+
+    const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+    const WELCOME: &str = "Welcome to budgetchat! What shall I call you?\n";
+
+    fn hex(text: &str) -> String {
+        testing::u8s_to_hex_str(text.as_bytes())
+    }
+
+    fn join(port: u16, name: &str, room: &str) -> TcpStream {
+        let mut client = connect(port);
+        assert_client_receives_bytes!(client, &hex(WELCOME), DEFAULT_TIMEOUT);
+        _ = client.write_all(format!("{name}\n").as_bytes());
+        assert_client_receives_bytes!(
+            client,
+            &hex(&format!("* The room contains: {room}\n")),
+            DEFAULT_TIMEOUT
+        );
+        client
+    }
+
+    #[test]
+    fn join_and_chat() {
+        let server = spawn_for_test();
+        let mut alice = join(server.port, "alice", "");
+        let mut bob = join(server.port, "bob", "alice");
+        assert_client_receives_bytes!(alice, &hex("* bob has entered the room\n"), DEFAULT_TIMEOUT);
+
+        _ = bob.write_all(b"hello\n");
+        assert_client_receives_bytes!(alice, &hex("[bob] hello\n"), DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn leave() {
+        let server = spawn_for_test();
+        let mut alice = join(server.port, "alice", "");
+        let bob = join(server.port, "bob", "alice");
+        assert_client_receives_bytes!(alice, &hex("* bob has entered the room\n"), DEFAULT_TIMEOUT);
+
+        _ = bob.shutdown(std::net::Shutdown::Both);
+        assert_client_receives_bytes!(alice, &hex("* bob has left the room\n"), DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn invalid_name() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+        assert_client_receives_bytes!(client, &hex(WELCOME), DEFAULT_TIMEOUT);
+        _ = client.write_all(b"not valid!\n");
+
+        // The server closes the connection without sending anything more.
+        let now = Instant::now();
+        let mut buffer = [0u8; 512];
+        loop {
+            match client.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => panic!("Unexpectedly received {:?}.", &buffer[..n]),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => (),
+                Err(e) => panic!("Client connection errored: {e:?}"),
+            }
+            assert!(
+                now.elapsed() < DEFAULT_TIMEOUT,
+                "Timeout reached waiting for disconnect."
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn conformance() {
+        let server = spawn_for_test();
+        assert_conforms(Problem::BudgetChat, server.port);
+    }
+
+    #[test]
+    fn broadcast_snapshot() {
+        let server = spawn_for_test();
+        let mut conversation = Conversation::new(server.port, Format::Text);
+        conversation.connect("alice");
+        conversation.send("alice", b"alice\n");
+        conversation.connect("bob");
+        conversation.send("bob", b"bob\n");
+        conversation.send("alice", b"hello bob\n");
+        conversation.send("bob", b"hi alice\n");
+        conversation.connect("mallory");
+        conversation.send("mallory", b"not valid!\n");
+        conversation.disconnect("bob");
+        assert_snapshot!(conversation, "broadcast");
+    }
+}
@@ -1,6 +1,12 @@
+mod clock;
+
+pub use clock::{Clock, SystemClock};
+
 use std::env;
 use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
-use std::thread;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
 pub const ASCII_NEWLINE: u8 = 10;
@@ -39,7 +45,15 @@ where
     F: Fn(TcpStream) + Clone + Send + Sync + 'static,
 {
     let listener = get_tcp_listener(port);
-    loop {
+    serve(listener, stream_handler, blocking, &ShutdownSignal::new());
+    unreachable!("Server stopped without being asked to shut down.");
+}
+
+pub fn serve<F>(listener: TcpListener, stream_handler: F, blocking: bool, shutdown: &ShutdownSignal)
+where
+    F: Fn(TcpStream) + Clone + Send + Sync + 'static,
+{
+    while !shutdown.is_triggered() {
         if let Ok((stream, _)) = listener.accept() {
             let thread_handler = stream_handler.clone();
             match blocking {
@@ -51,6 +65,87 @@ where
     }
 }
 
+/// Tells a server's main loop to stop accepting connections and return.
+#[derive(Clone, Default)]
+pub struct ShutdownSignal(Arc<AtomicBool>);
+impl ShutdownSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn trigger(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+    pub fn is_triggered(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// A server running on a background thread. Shutting down (or dropping) the handle stops the
+/// server's main loop and waits for it to finish; connections already handed off to their own
+/// threads are left to finish on their own.
+pub struct ServerHandle {
+    pub port: u16,
+    shutdown: ShutdownSignal,
+    thread: Option<JoinHandle<()>>,
+}
+impl ServerHandle {
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+    fn stop(&mut self) {
+        self.shutdown.trigger();
+        if let Some(thread) = self.thread.take() {
+            _ = thread.join();
+        }
+    }
+}
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Run a TCP server on an OS-assigned loopback port, for tests.
+pub fn spawn_tcp_for_test<F>(server: F) -> ServerHandle
+where
+    F: FnOnce(TcpListener, ShutdownSignal) + Send + 'static,
+{
+    let listener = TcpListener::bind(("127.0.0.1", 0)).expect("Could not bind to port.");
+    listener
+        .set_nonblocking(true)
+        .expect("Could not set TCP listener as non-blocking.");
+    let port = listener.local_addr().expect("Could not get port.").port();
+    spawn_for_test(port, move |shutdown| server(listener, shutdown))
+}
+
+/// Run a UDP server on an OS-assigned loopback port, for tests.
+pub fn spawn_udp_for_test<F>(server: F) -> ServerHandle
+where
+    F: FnOnce(UdpSocket, ShutdownSignal) + Send + 'static,
+{
+    let socket = UdpSocket::bind(("127.0.0.1", 0)).expect("Could not bind to port.");
+    // A blocking receive would never notice the shutdown signal.
+    socket
+        .set_nonblocking(true)
+        .expect("Could not set UDP socket as non-blocking.");
+    let port = socket.local_addr().expect("Could not get port.").port();
+    spawn_for_test(port, move |shutdown| server(socket, shutdown))
+}
+
+fn spawn_for_test<F>(port: u16, server: F) -> ServerHandle
+where
+    F: FnOnce(ShutdownSignal) + Send + 'static,
+{
+    println!("Integration test: running application on port {port}.");
+    let shutdown = ShutdownSignal::new();
+    let thread_shutdown = shutdown.clone();
+    ServerHandle {
+        port,
+        shutdown,
+        thread: Some(thread::spawn(move || server(thread_shutdown))),
+    }
+}
+
 pub fn get_port() -> u16 {
     let args: Vec<String> = env::args().collect();
     let port: u16 = if args.len() >= 2 {
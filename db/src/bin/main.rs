@@ -0,0 +1,6 @@
+use common::{get_udp_listener, ShutdownSignal};
+
+fn main() {
+    let socket = get_udp_listener(None);
+    db::serve(socket, &ShutdownSignal::new());
+}
@@ -0,0 +1,115 @@
+use common::{spawn_udp_for_test, ServerHandle, ShutdownSignal, THREAD_SLOW_DOWN};
+use std::collections::HashMap;
+use std::net::UdpSocket;
+use std::thread;
+
+const BUFFER_SIZE: usize = 1_000;
+const VERSION_KEY: &[u8] = b"version";
+const VERSION_STRING: &[u8] = b"Zan's Key-Value Store 0.1.0";
+// When testing with netcat, strip newlines that are added to the ends of packets.
+const SHOULD_HANDLE_NEWLINES: bool = false;
+
+struct Database {
+    items: HashMap<Vec<u8>, Vec<u8>>,
+}
+impl Database {
+    fn new() -> Self {
+        Self {
+            items: HashMap::new(),
+        }
+    }
+    fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        if key.as_slice() == VERSION_KEY {
+            return;
+        }
+        self.items.insert(key, value);
+    }
+    fn query(&self, key: &[u8]) -> Option<&[u8]> {
+        if key == VERSION_KEY {
+            return Some(VERSION_STRING);
+        }
+        match self.items.get(key) {
+            Some(value) => Some(value.as_slice()),
+            None => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Request<'a> {
+    Insert { key: &'a [u8], value: &'a [u8] },
+    Retrieve { key: &'a [u8] },
+}
+impl<'a> Request<'a> {
+    /// Everything before the first equals sign is the key of an insert; the value may contain
+    /// further equals signs. Without one, the whole request is the key to retrieve.
+    fn parse(request: &'a [u8]) -> Self {
+        match request.iter().position(|&byte| byte == b'=') {
+            Some(position) => Self::Insert {
+                key: &request[..position],
+                value: &request[position + 1..],
+            },
+            None => Self::Retrieve { key: request },
+        }
+    }
+}
+
+pub fn serve(socket: UdpSocket, shutdown: &ShutdownSignal) {
+    let mut database = Database::new();
+
+    let mut buffer = [0u8; BUFFER_SIZE];
+    while !shutdown.is_triggered() {
+        if let Ok((length, source)) = socket.recv_from(&mut buffer) {
+            let mut request: Vec<u8> = vec![];
+            request.extend_from_slice(&buffer[..length]);
+
+            if SHOULD_HANDLE_NEWLINES {
+                if let Some(&b'\n') = request.last() {
+                    request.pop();
+                }
+            }
+
+            match Request::parse(&request) {
+                Request::Insert { key, value } => database.insert(key.to_vec(), value.to_vec()),
+                Request::Retrieve { key } => {
+                    if let Some(value) = database.query(key) {
+                        let mut response: Vec<u8> = vec![];
+                        response.extend_from_slice(key);
+                        response.push(b'=');
+                        response.extend_from_slice(value);
+                        if SHOULD_HANDLE_NEWLINES {
+                            response.push(b'\n');
+                        }
+                        _ = socket.send_to(&response, source);
+                    }
+                }
+            }
+        }
+        thread::sleep(THREAD_SLOW_DOWN);
+    }
+}
+
+pub fn spawn_for_test() -> ServerHandle {
+    spawn_udp_for_test(|socket, shutdown| serve(socket, &shutdown))
+}
+
+/// Request splitting, for the `db_request` fuzz target.
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing {
+    use super::{Database, Request};
+
+    /// Split a request; anything inserted must then be retrievable unchanged.
+    pub fn request(data: &[u8]) {
+        let mut database = Database::new();
+        match Request::parse(data) {
+            Request::Insert { key, value } => {
+                assert_eq!(data.len(), key.len() + 1 + value.len());
+                database.insert(key.to_vec(), value.to_vec());
+                if key != super::VERSION_KEY {
+                    assert_eq!(Some(value), database.query(key));
+                }
+            }
+            Request::Retrieve { key } => assert!(!key.contains(&b'=')),
+        }
+    }
+}
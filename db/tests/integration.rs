@@ -0,0 +1,88 @@
+//! Integration tests for Unusual Database Program.
+
+#[cfg(test)]
+mod test {
+    use db::spawn_for_test;
+    use std::time::Duration;
+    use testing::{
+        conformance::{assert_conforms, Problem},
+        udp::UdpTestClient,
+    };
+
+    const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+    #[test]
+    fn insert_and_retrieve() {
+        let server = spawn_for_test();
+        let mut client = UdpTestClient::connect(server.port);
+
+        client.send(b"foo=bar");
+        client.send(b"foo");
+        client.expect_datagram(b"foo=bar", DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn value_containing_equals() {
+        let server = spawn_for_test();
+        let mut client = UdpTestClient::connect(server.port);
+
+        client.send(b"foo=bar=baz");
+        client.send(b"foo");
+        client.expect_datagram(b"foo=bar=baz", DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn version_cannot_be_modified() {
+        let server = spawn_for_test();
+        let mut client = UdpTestClient::connect(server.port);
+
+        client.send(b"version=hacked");
+        client.send(b"version");
+        client.expect_datagram(b"version=Zan's Key-Value Store 0.1.0", DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn unknown_key() {
+        let server = spawn_for_test();
+        let mut client = UdpTestClient::connect(server.port);
+
+        client.send(b"missing");
+        client.expect_no_reply(Duration::from_millis(100));
+    }
+
+    #[test]
+    fn reordered_inserts() {
+        let server = spawn_for_test();
+        let mut client = UdpTestClient::connect(server.port);
+
+        // Writes to the same key, so the value retrieved depends on the order they arrive in (which
+        // over loopback is the order they were sent in).
+        let sent = client.send_reordered(&[b"k=1", b"k=2", b"k=3"]);
+        client.send(b"k");
+        client.expect_datagram(sent[sent.len() - 1], DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn retries_survive_loss() {
+        let server = spawn_for_test();
+        let mut client = UdpTestClient::connect(server.port).with_loss(0.5);
+
+        // Like any UDP client, retry until a response arrives.
+        for _ in 0..50 {
+            client.send(b"lossy=value");
+            if client.send(b"lossy") {
+                if let Some(response) = client.receive(Duration::from_millis(50)) {
+                    assert_eq!(b"lossy=value".to_vec(), response);
+                    return;
+                }
+            }
+        }
+        panic!("No response after 50 attempts.");
+    }
+
+    #[test]
+    fn conformance() {
+        let server = spawn_for_test();
+        assert_conforms(Problem::UnusualDatabase, server.port);
+    }
+}
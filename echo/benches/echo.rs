@@ -0,0 +1,57 @@
+//! End-to-end loopback benchmarks: each round trip goes through `common::serve` (as used by
+//! `common::run`) and the echo handler, over a real TCP connection.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use echo::{spawn_for_test, spawn_for_test_with_mode, Mode};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+
+fn loopback(c: &mut Criterion) {
+    let server = spawn_for_test();
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+    stream.set_nodelay(true).unwrap();
+
+    let mut group = c.benchmark_group("echo/loopback");
+    for size in [16usize, 1_024, 64 * 1_024] {
+        let payload: Vec<u8> = (0..size).map(|i| i as u8).collect();
+        let mut response = vec![0u8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &payload, |b, payload| {
+            b.iter(|| {
+                stream.write_all(payload).unwrap();
+                stream.read_exact(&mut response).unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Large transfers through the copying and the `splice` handlers, writing and reading at the same
+/// time so that neither side waits for the other's buffers to drain.
+fn throughput(c: &mut Criterion) {
+    const SIZE: usize = 4 * 1_048_576;
+    let payload: Vec<u8> = (0..SIZE).map(|i| i as u8).collect();
+    let mut response = vec![0u8; SIZE];
+
+    let mut group = c.benchmark_group("echo/throughput");
+    group.throughput(Throughput::Bytes(SIZE as u64));
+    group.sample_size(20);
+    for mode in [Mode::Immediate, Mode::Splice] {
+        let server = spawn_for_test_with_mode(mode);
+        let mut stream = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        group.bench_function(BenchmarkId::from_parameter(format!("{mode:?}")), |b| {
+            b.iter(|| {
+                thread::scope(|scope| {
+                    scope.spawn(|| writer.write_all(&payload).unwrap());
+                    stream.read_exact(&mut response).unwrap();
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, loopback, throughput);
+criterion_main!(benches);
@@ -1,6 +1,76 @@
-use common::run;
-use echo::handle_stream_immediate;
+use common::{get_udp_listener, run, ShutdownSignal, SystemClock, DEFAULT_PORT};
+use echo::{
+    udp::{serve_udp, RateLimit},
+    Mode, Service,
+};
+use std::env;
+use std::sync::Arc;
+
+const USAGE: &str = "Usage: echo [echo|discard|chargen|daytime|time] [port] \
+                     [--mode immediate|buffered|splice] [--max-buffered <bytes>] \
+                     [--udp [--rate-limit <datagrams per second>]]";
 
 fn main() {
-    run(handle_stream_immediate, None, false);
+    let mut service = Service::Echo;
+    let mut port = DEFAULT_PORT;
+    let mut udp = false;
+    let mut rate_limit: Option<RateLimit> = None;
+    let mut mode: Option<Mode> = None;
+    let mut max_buffered: Option<usize> = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--udp" => udp = true,
+            "--rate-limit" => {
+                let per_second = args.next().expect(USAGE).parse().expect(USAGE);
+                // A limit of zero would drop every datagram.
+                if per_second == 0 {
+                    panic!("{USAGE}");
+                }
+                rate_limit = Some(RateLimit {
+                    burst: per_second,
+                    per_second,
+                });
+            }
+            "--mode" => mode = Some(args.next().expect(USAGE).parse().expect(USAGE)),
+            "--max-buffered" => {
+                max_buffered = Some(args.next().expect(USAGE).parse().expect(USAGE))
+            }
+            _ => match arg.parse::<u16>() {
+                Ok(number) => port = number,
+                Err(_) => service = arg.parse().expect(USAGE),
+            },
+        }
+    }
+    if rate_limit.is_some() && !udp {
+        panic!("{USAGE}");
+    }
+    // Modes only change how TCP echo answers, so they make no sense with anything else.
+    let tcp_echo = !udp && service == Service::Echo;
+    if !tcp_echo && (mode.is_some() || max_buffered.is_some()) {
+        panic!("{USAGE}");
+    }
+    // A buffer limit implies buffered mode, and makes no sense with any other.
+    let mode = match (mode, max_buffered) {
+        (None | Some(Mode::Buffered { .. }), Some(limit)) => Mode::Buffered { limit },
+        (Some(_), Some(_)) => panic!("{USAGE}"),
+        (mode, None) => mode.unwrap_or(Mode::Immediate),
+    };
+
+    if udp {
+        println!("Serving {service:?} over UDP (rate limit: {rate_limit:?})...");
+        let socket = get_udp_listener(Some(port));
+        serve_udp(
+            socket,
+            service,
+            rate_limit,
+            Arc::new(SystemClock),
+            &ShutdownSignal::new(),
+        );
+    } else {
+        println!("Serving {service:?} over TCP in {mode:?} mode...");
+        let handler = service.handler_with_reports(mode, |report| println!("{report}"));
+        run(handler, Some(port), false);
+    }
 }
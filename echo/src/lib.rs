@@ -1,41 +1,318 @@
-use common::BUFFER_SIZE;
-use std::io::{ErrorKind, Read, Write};
-use std::net::{Shutdown, TcpStream};
+pub mod services;
+#[cfg(target_os = "linux")]
+pub mod splice;
+pub mod udp;
+
+use common::{
+    serve, spawn_tcp_for_test, spawn_udp_for_test, Clock, ServerHandle, SystemClock, BUFFER_SIZE,
+};
+use std::fmt::Display;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::str::FromStr;
+use std::sync::Arc;
+use udp::RateLimit;
+
+pub const DEFAULT_MAX_BUFFERED: usize = 1_048_576;
+/// Sent (instead of anything buffered) when a client sends more than the buffered mode's limit,
+/// before the server stops sending. Anything else the client sends is discarded.
+pub const BUFFER_EXCEEDED_RESPONSE: &[u8] = b"ERROR: echo buffer limit exceeded\n";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// Echo data back as soon as it arrives.
+    Immediate,
+    /// Hold everything until the client finishes sending, then echo it all back at once.
+    Buffered { limit: usize },
+    /// Like immediate, but moving data inside the kernel with `splice` (Linux only; elsewhere the
+    /// same as immediate).
+    Splice,
+}
+impl Mode {
+    pub fn handler(self) -> impl Fn(TcpStream) + Clone + Send + Sync + 'static {
+        move |stream| _ = self.echo(stream)
+    }
+
+    /// Echo one connection in this mode, until the client is done.
+    pub fn echo(self, stream: TcpStream) -> EchoReport {
+        match self {
+            Self::Immediate => echo_immediate(stream),
+            Self::Buffered { limit } => echo_buffered(stream, limit),
+            #[cfg(target_os = "linux")]
+            Self::Splice => splice::echo_splice(stream),
+            #[cfg(not(target_os = "linux"))]
+            Self::Splice => echo_immediate(stream),
+        }
+    }
+}
+impl FromStr for Mode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "immediate" => Ok(Self::Immediate),
+            "buffered" => Ok(Self::Buffered {
+                limit: DEFAULT_MAX_BUFFERED,
+            }),
+            "splice" => Ok(Self::Splice),
+            _ => Err(format!(
+                "Unknown mode \"{s}\" (immediate, buffered, splice)."
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Service {
+    /// Send back everything received (RFC 862), and the Protohackers smoke test.
+    Echo,
+    /// Ignore everything received (RFC 863).
+    Discard,
+    /// Send a rotating pattern of printable characters (RFC 864).
+    Chargen,
+    /// Send the date and time in human-readable form (RFC 867).
+    Daytime,
+    /// Send the time as seconds since 1900 (RFC 868).
+    Time,
+}
+impl Service {
+    /// TCP connection handler; `mode` only applies to echo.
+    pub fn handler(self, mode: Mode) -> impl Fn(TcpStream) + Clone + Send + Sync + 'static {
+        self.handler_with_reports(mode, |_| ())
+    }
+
+    /// The same, passing `report` what happened on each echo connection.
+    pub fn handler_with_reports<R>(
+        self,
+        mode: Mode,
+        report: R,
+    ) -> impl Fn(TcpStream) + Clone + Send + Sync + 'static
+    where
+        R: Fn(EchoReport) + Clone + Send + Sync + 'static,
+    {
+        move |stream| match self {
+            Self::Echo => report(mode.echo(stream)),
+            Self::Discard => services::handle_discard(stream),
+            Self::Chargen => services::handle_chargen(stream),
+            Self::Daytime => services::handle_daytime(stream),
+            Self::Time => services::handle_time(stream),
+        }
+    }
+}
+impl FromStr for Service {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "echo" => Ok(Self::Echo),
+            "discard" => Ok(Self::Discard),
+            "chargen" => Ok(Self::Chargen),
+            "daytime" => Ok(Self::Daytime),
+            "time" => Ok(Self::Time),
+            _ => Err(format!(
+                "Unknown service \"{s}\" (echo, discard, chargen, daytime, time)."
+            )),
+        }
+    }
+}
+
+/// What happened to one connection: how much was echoed back, and the error that ended it early
+/// (if any).
+#[derive(Debug)]
+pub struct EchoReport {
+    pub peer: Option<SocketAddr>,
+    pub bytes: u64,
+    pub error: Option<io::Error>,
+}
+impl EchoReport {
+    fn new(stream: &TcpStream) -> Self {
+        Self {
+            peer: stream.peer_addr().ok(),
+            bytes: 0,
+            error: None,
+        }
+    }
+}
+impl Display for EchoReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let peer = self
+            .peer
+            .map_or("unknown peer".to_string(), |peer| peer.to_string());
+        write!(f, "Echoed {} bytes to {peer}", self.bytes)?;
+        match &self.error {
+            Some(error) => write!(f, " before failing: {error}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Close only the writing half once everything has been sent, so that nothing still in flight
+/// is lost. The client sees end-of-file after the last echoed byte.
+fn finish(stream: &mut TcpStream, report: &mut EchoReport) {
+    if let Err(err) = stream
+        .flush()
+        .and_then(|()| stream.shutdown(Shutdown::Write))
+    {
+        report.error.get_or_insert(err);
+    }
+}
 
 // Smoke Test (Echo Server)
-pub fn handle_stream_buffer(mut stream: TcpStream) {
+pub fn handle_stream_buffer(stream: TcpStream) {
+    handle_stream_buffer_with_limit(stream, DEFAULT_MAX_BUFFERED);
+}
+
+pub fn handle_stream_buffer_with_limit(stream: TcpStream, limit: usize) {
+    _ = echo_buffered(stream, limit);
+}
+
+/// Hold everything until the client half-closes (or closes), then send it all back.
+pub fn echo_buffered(mut stream: TcpStream, limit: usize) -> EchoReport {
+    let mut report = EchoReport::new(&stream);
     let mut contents: Vec<u8> = vec![];
     let mut buffer = [0u8; BUFFER_SIZE];
     'connected: loop {
         match stream.read(&mut buffer) {
             Ok(0) => {
-                _ = stream.write_all(&contents);
+                match stream.write_all(&contents) {
+                    Ok(()) => report.bytes = contents.len() as u64,
+                    Err(err) => report.error = Some(err),
+                }
                 break 'connected;
             }
+            Ok(n) if contents.len() + n > limit => {
+                if let Err(err) = stream.write_all(BUFFER_EXCEEDED_RESPONSE) {
+                    report.error = Some(err);
+                    break 'connected;
+                }
+                finish(&mut stream, &mut report);
+                // Closing with unread data would reset the connection (and could destroy the
+                // response before the client reads it), so discard the rest of the input first.
+                while let Ok(1..) = stream.read(&mut buffer) {}
+                return report;
+            }
             Ok(n) => contents.extend_from_slice(&buffer[..n]),
             Err(ref e) if e.kind() == ErrorKind::WouldBlock => (),
-            Err(err) => panic!("Error processing stream: {err:?}"),
+            Err(err) => {
+                report.error = Some(err);
+                break 'connected;
+            }
         }
     }
 
-    _ = stream.shutdown(Shutdown::Both);
+    finish(&mut stream, &mut report);
+    report
 }
 
 // Smoke Test (HexCat)
-pub fn handle_stream_immediate(mut stream: TcpStream) {
+pub fn handle_stream_immediate(stream: TcpStream) {
+    _ = echo_immediate(stream);
+}
+
+/// Send back everything as soon as it arrives, until the client half-closes (or closes).
+pub fn echo_immediate(mut stream: TcpStream) -> EchoReport {
+    let mut report = EchoReport::new(&stream);
     let mut buffer = [0u8; BUFFER_SIZE];
     'connected: loop {
         match stream.read(&mut buffer) {
             Ok(0) => break 'connected,
-            Ok(n) => {
-                if stream.write_all(&buffer[..n]).is_err() {
+            Ok(n) => match stream.write_all(&buffer[..n]) {
+                Ok(()) => report.bytes += n as u64,
+                Err(err) => {
+                    report.error = Some(err);
                     break 'connected;
                 }
-            }
+            },
             Err(ref e) if e.kind() == ErrorKind::WouldBlock => (),
-            Err(err) => panic!("Error processing stream: {err:?}"),
+            Err(err) => {
+                report.error = Some(err);
+                break 'connected;
+            }
         }
     }
 
-    _ = stream.shutdown(Shutdown::Both);
+    finish(&mut stream, &mut report);
+    report
+}
+
+pub fn spawn_for_test() -> ServerHandle {
+    spawn_for_test_with_mode(Mode::Immediate)
+}
+
+pub fn spawn_for_test_with_mode(mode: Mode) -> ServerHandle {
+    spawn_for_test_with_service(Service::Echo, mode)
+}
+
+pub fn spawn_for_test_with_service(service: Service, mode: Mode) -> ServerHandle {
+    spawn_tcp_for_test(move |listener, shutdown| {
+        serve(listener, service.handler(mode), false, &shutdown)
+    })
+}
+
+pub fn spawn_udp_for_test_with_service(service: Service) -> ServerHandle {
+    spawn_udp_for_test_with_limit(service, None, Arc::new(SystemClock))
+}
+
+pub fn spawn_udp_for_test_with_limit(
+    service: Service,
+    rate_limit: Option<RateLimit>,
+    clock: Arc<dyn Clock>,
+) -> ServerHandle {
+    spawn_udp_for_test(move |socket, shutdown| {
+        udp::serve_udp(socket, service, rate_limit, clock, &shutdown)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{echo_buffered, echo_immediate, EchoReport, BUFFER_EXCEEDED_RESPONSE};
+    use std::io::{Read, Write};
+    use std::net::{Shutdown, TcpListener, TcpStream};
+    use std::thread;
+
+    /// Run `echo` against one client that sends `sent` then half-closes, returning the report and
+    /// everything the client read back.
+    fn echo_once<F>(echo: F, sent: &'static [u8]) -> (EchoReport, Vec<u8>)
+    where
+        F: FnOnce(TcpStream) -> EchoReport + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || echo(listener.accept().unwrap().0));
+        let mut client = TcpStream::connect(address).unwrap();
+        client.write_all(sent).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let mut received = vec![];
+        client.read_to_end(&mut received).unwrap();
+        (server.join().unwrap(), received)
+    }
+
+    #[test]
+    fn test_immediate_report() {
+        let (report, received) = echo_once(echo_immediate, b"hello, world");
+        assert_eq!(b"hello, world", &received[..]);
+        assert_eq!(12, report.bytes);
+        assert!(report.error.is_none());
+        assert!(report
+            .to_string()
+            .starts_with("Echoed 12 bytes to 127.0.0.1:"));
+    }
+
+    #[test]
+    fn test_buffered_report() {
+        let (report, received) = echo_once(|stream| echo_buffered(stream, 16), b"hello");
+        assert_eq!(b"hello", &received[..]);
+        assert_eq!(5, report.bytes);
+
+        let (report, received) = echo_once(|stream| echo_buffered(stream, 4), b"hello");
+        assert_eq!(BUFFER_EXCEEDED_RESPONSE, &received[..]);
+        assert_eq!(0, report.bytes);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_splice_report() {
+        let (report, received) = echo_once(crate::splice::echo_splice, b"hello, world");
+        assert_eq!(b"hello, world", &received[..]);
+        assert_eq!(12, report.bytes);
+        assert!(report.error.is_none());
+    }
 }
@@ -0,0 +1,180 @@
+//! Classic inetd diagnostic services: discard (RFC 863), character generator (RFC 864), daytime
+//! (RFC 867) and time (RFC 868). These are the TCP handlers; `udp` serves the same responses.
+
+use common::BUFFER_SIZE;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The character generator rotates through the 95 printable ASCII characters.
+const CHARGEN_FIRST: u8 = b' ';
+const CHARGEN_CHARACTERS: usize = 95;
+const CHARGEN_LINE_LENGTH: usize = 72;
+// As many whole lines (with CRLF) as fit in the 512 characters RFC 864 allows for a UDP reply.
+pub(crate) const CHARGEN_UDP_LINES: usize = 512 / (CHARGEN_LINE_LENGTH + 2);
+// Seconds between the RFC 868 epoch (1900-01-01) and the Unix epoch (1970-01-01).
+const TIME_EPOCH_OFFSET: u64 = 2_208_988_800;
+const WEEKDAYS: [&str; 7] = [
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Throw away everything the client sends.
+pub fn handle_discard(mut stream: TcpStream) {
+    let mut buffer = [0u8; BUFFER_SIZE];
+    'connected: loop {
+        match stream.read(&mut buffer) {
+            Ok(0) => break 'connected,
+            Ok(_) => (),
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => (),
+            Err(_) => break 'connected,
+        }
+    }
+
+    _ = stream.shutdown(Shutdown::Both);
+}
+
+/// Send lines of the character generator pattern until the client goes away.
+pub fn handle_chargen(mut stream: TcpStream) {
+    let mut line: usize = 0;
+    'connected: loop {
+        if stream.write_all(&chargen_line(line)).is_err() {
+            break 'connected;
+        }
+        line += 1;
+    }
+
+    _ = stream.shutdown(Shutdown::Both);
+}
+
+/// Send the current date and time as text, then close the connection.
+pub fn handle_daytime(mut stream: TcpStream) {
+    _ = stream.write_all(daytime(SystemTime::now()).as_bytes());
+    _ = stream.shutdown(Shutdown::Write);
+}
+
+/// Send the current time as seconds since 1900, then close the connection.
+pub fn handle_time(mut stream: TcpStream) {
+    _ = stream.write_all(&time(SystemTime::now()));
+    _ = stream.shutdown(Shutdown::Write);
+}
+
+/// Line `n` of the pattern: 72 characters starting one further along each line, then CRLF.
+pub(crate) fn chargen_line(n: usize) -> Vec<u8> {
+    let mut line: Vec<u8> = (0..CHARGEN_LINE_LENGTH)
+        .map(|i| CHARGEN_FIRST + ((n + i) % CHARGEN_CHARACTERS) as u8)
+        .collect();
+    line.extend_from_slice(b"\r\n");
+    line
+}
+
+/// RFC 867 leaves the format open, so use its suggestion: "Weekday, Month Day, Year HH:MM:SS-Zone".
+pub(crate) fn daytime(now: SystemTime) -> String {
+    let seconds = now
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let days = seconds / 86_400;
+    let (year, month, day) = civil_from_days(days as i64);
+    let (hours, minutes, seconds) = (
+        (seconds % 86_400) / 3_600,
+        (seconds % 3_600) / 60,
+        seconds % 60,
+    );
+    format!(
+        "{}, {} {day}, {year} {hours:02}:{minutes:02}:{seconds:02}-UTC\r\n",
+        WEEKDAYS[(days % 7) as usize],
+        MONTHS[month as usize - 1],
+    )
+}
+
+/// Seconds since 1900-01-01 00:00 UTC, as a 32-bit big-endian number (which wraps in 2036).
+pub(crate) fn time(now: SystemTime) -> [u8; 4] {
+    let seconds = now
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    ((seconds + TIME_EPOCH_OFFSET) as u32).to_be_bytes()
+}
+
+/// Convert days since the Unix epoch to a (year, month, day) date in the proleptic Gregorian
+/// calendar, using Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chargen_line, civil_from_days, daytime, time};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_chargen_line() {
+        let first = chargen_line(0);
+        assert_eq!(74, first.len());
+        assert!(first.starts_with(b" !\"#$%&'()*+,-./0123456789"));
+        assert!(first.ends_with(b"efg\r\n"));
+        // Each line starts one character further along, wrapping after the tilde.
+        assert!(chargen_line(1).starts_with(b"!\"#$"));
+        assert!(chargen_line(94).starts_with(b"~ !\"#"));
+        assert_eq!(first, chargen_line(95));
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((2000, 2, 29), civil_from_days(11_016));
+        assert_eq!((1969, 12, 31), civil_from_days(-1));
+    }
+
+    #[test]
+    fn test_daytime() {
+        assert_eq!(
+            "Sunday, February 20, 2022 12:34:56-UTC\r\n",
+            daytime(UNIX_EPOCH + Duration::from_secs(1_645_360_496))
+        );
+    }
+
+    #[test]
+    fn test_time() {
+        // Examples from RFC 868.
+        assert_eq!(2_208_988_800u32.to_be_bytes(), time(UNIX_EPOCH));
+        assert_eq!(
+            2_398_291_200u32.to_be_bytes(),
+            time(UNIX_EPOCH + Duration::from_secs(2_398_291_200 - 2_208_988_800))
+        );
+    }
+}
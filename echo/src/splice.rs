@@ -0,0 +1,93 @@
+//! Zero-copy echo for Linux: `splice` moves data from the socket into a pipe and from the pipe
+//! back into the socket, so the bytes never pass through a userspace buffer.
+
+use crate::{echo_immediate, finish, EchoReport};
+use std::io;
+use std::net::TcpStream;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+// The default pipe capacity, so that each move from the socket fits in the pipe.
+const CHUNK_SIZE: usize = 65_536;
+
+// Smoke Test (Echo Server)
+pub fn handle_stream_splice(stream: TcpStream) {
+    _ = echo_splice(stream);
+}
+
+/// Behaves like `echo_immediate`, falling back to it if the kernel cannot splice this socket.
+pub fn echo_splice(mut stream: TcpStream) -> EchoReport {
+    let mut report = EchoReport::new(&stream);
+    let (pipe_out, pipe_in) = match pipe() {
+        Ok(pipe) => pipe,
+        Err(_) => return echo_immediate(stream),
+    };
+    let socket = stream.as_raw_fd();
+    'connected: loop {
+        let received = match splice(socket, pipe_in.as_raw_fd(), CHUNK_SIZE) {
+            Ok(0) => break 'connected,
+            Ok(n) => n,
+            Err(err) if report.bytes == 0 && err.raw_os_error() == Some(libc::EINVAL) => {
+                return echo_immediate(stream);
+            }
+            Err(err) => {
+                report.error = Some(err);
+                break 'connected;
+            }
+        };
+        let mut pending = received;
+        while pending > 0 {
+            match splice(pipe_out.as_raw_fd(), socket, pending) {
+                Ok(0) => {
+                    report.error = Some(io::ErrorKind::WriteZero.into());
+                    break 'connected;
+                }
+                Ok(n) => pending -= n,
+                Err(err) => {
+                    report.error = Some(err);
+                    break 'connected;
+                }
+            }
+        }
+        report.bytes += received as u64;
+    }
+
+    finish(&mut stream, &mut report);
+    report
+}
+
+/// The (read, write) ends of a new pipe.
+fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds: [RawFd; 2] = [0; 2];
+    // SAFETY: `fds` has room for the two descriptors `pipe2` writes, which are then owned here.
+    unsafe {
+        if libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])))
+    }
+}
+
+/// Move up to `length` bytes from `from` to `to`, retrying if interrupted.
+fn splice(from: RawFd, to: RawFd, length: usize) -> io::Result<usize> {
+    loop {
+        // SAFETY: both descriptors are open for the duration of the call, and null offsets are
+        // allowed for sockets and pipes.
+        let moved = unsafe {
+            libc::splice(
+                from,
+                std::ptr::null_mut(),
+                to,
+                std::ptr::null_mut(),
+                length,
+                libc::SPLICE_F_MOVE,
+            )
+        };
+        match moved {
+            -1 => match io::Error::last_os_error() {
+                err if err.kind() == io::ErrorKind::Interrupted => continue,
+                err => return Err(err),
+            },
+            n => return Ok(n as usize),
+        }
+    }
+}
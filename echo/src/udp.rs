@@ -0,0 +1,189 @@
+//! Every service over UDP, with optional per-source rate limiting so that the server cannot be
+//! used to reflect (or amplify) traffic at a spoofed source address.
+
+use crate::services::{chargen_line, daytime, time, CHARGEN_UDP_LINES};
+use crate::Service;
+use common::{Clock, ShutdownSignal, THREAD_SLOW_DOWN};
+use std::collections::HashMap;
+use std::net::{IpAddr, UdpSocket};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+// Large enough for any UDP payload, so echoed datagrams are never truncated.
+const DATAGRAM_SIZE: usize = 65_536;
+// At most this many sources are tracked; datagrams from any others are dropped until there is room.
+const MAX_TRACKED_SOURCES: usize = 10_000;
+// How often to forget sources whose buckets have refilled (and so are no different from new).
+const PRUNE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Token bucket: each source may send `burst` datagrams at once, refilled at `per_second`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_second: u32,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    clock: Arc<dyn Clock>,
+    sources: HashMap<IpAddr, Bucket>,
+    pruned: Instant,
+}
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            limit,
+            pruned: clock.now(),
+            sources: HashMap::new(),
+            clock,
+        }
+    }
+
+    /// Whether a datagram from `source` should be answered, using up one of its tokens if so.
+    pub(crate) fn allow(&mut self, source: IpAddr) -> bool {
+        let now = self.clock.now();
+        let (burst, rate) = (self.limit.burst as f64, self.limit.per_second as f64);
+        let refill = |bucket: &Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+            (bucket.tokens + elapsed * rate).min(burst)
+        };
+
+        if now.saturating_duration_since(self.pruned) >= PRUNE_INTERVAL {
+            self.sources.retain(|_, bucket| refill(bucket) < burst);
+            self.pruned = now;
+        }
+        if self.sources.len() >= MAX_TRACKED_SOURCES && !self.sources.contains_key(&source) {
+            // Fail closed, so a flood from spoofed sources cannot make room for itself.
+            return false;
+        }
+        let bucket = self.sources.entry(source).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        bucket.tokens = refill(bucket);
+        bucket.updated = now;
+        match bucket.tokens >= 1.0 {
+            true => {
+                bucket.tokens -= 1.0;
+                true
+            }
+            false => false,
+        }
+    }
+}
+
+/// Reply to every datagram according to the service (discard never replies), dropping any from
+/// sources that have exceeded the rate limit.
+pub fn serve_udp(
+    socket: UdpSocket,
+    service: Service,
+    rate_limit: Option<RateLimit>,
+    clock: Arc<dyn Clock>,
+    shutdown: &ShutdownSignal,
+) {
+    let mut limiter = rate_limit.map(|limit| RateLimiter::new(limit, clock));
+    let mut buffer = vec![0u8; DATAGRAM_SIZE];
+    // Successive chargen replies carry on the pattern from where the last one stopped.
+    let mut chargen_offset: usize = 0;
+    while !shutdown.is_triggered() {
+        if let Ok((length, source)) = socket.recv_from(&mut buffer) {
+            if let Some(limiter) = limiter.as_mut() {
+                if !limiter.allow(source.ip()) {
+                    continue;
+                }
+            }
+            let response: Option<Vec<u8>> = match service {
+                Service::Echo => Some(buffer[..length].to_vec()),
+                Service::Discard => None,
+                Service::Chargen => {
+                    let lines = (chargen_offset..chargen_offset + CHARGEN_UDP_LINES)
+                        .flat_map(chargen_line)
+                        .collect();
+                    chargen_offset += CHARGEN_UDP_LINES;
+                    Some(lines)
+                }
+                Service::Daytime => Some(daytime(SystemTime::now()).into_bytes()),
+                Service::Time => Some(time(SystemTime::now()).to_vec()),
+            };
+            if let Some(response) = response {
+                _ = socket.send_to(&response, source);
+            }
+        }
+        thread::sleep(THREAD_SLOW_DOWN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimit, RateLimiter, MAX_TRACKED_SOURCES, PRUNE_INTERVAL};
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use testing::clock::TestClock;
+
+    const ALICE: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    const BOB: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+
+    #[test]
+    fn test_burst_then_refill() {
+        let clock = Arc::new(TestClock::new());
+        let limit = RateLimit {
+            burst: 3,
+            per_second: 2,
+        };
+        let mut limiter = RateLimiter::new(limit, clock.clone());
+        assert_eq!(
+            vec![true, true, true, false],
+            (0..4).map(|_| limiter.allow(ALICE)).collect::<Vec<_>>()
+        );
+
+        clock.advance(Duration::from_millis(499));
+        assert!(!limiter.allow(ALICE));
+        clock.advance(Duration::from_millis(1));
+        assert!(limiter.allow(ALICE));
+        assert!(!limiter.allow(ALICE));
+
+        // Refills never exceed the burst.
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(3, (0..10).filter(|_| limiter.allow(ALICE)).count());
+    }
+
+    #[test]
+    fn test_sources_are_separate() {
+        let clock = Arc::new(TestClock::new());
+        let limit = RateLimit {
+            burst: 1,
+            per_second: 1,
+        };
+        let mut limiter = RateLimiter::new(limit, clock);
+        assert!(limiter.allow(ALICE));
+        assert!(!limiter.allow(ALICE));
+        assert!(limiter.allow(BOB));
+    }
+
+    #[test]
+    fn test_tracked_sources_are_capped() {
+        let clock = Arc::new(TestClock::new());
+        let limit = RateLimit {
+            burst: 1,
+            per_second: 1,
+        };
+        let mut limiter = RateLimiter::new(limit, clock.clone());
+        for n in 0..MAX_TRACKED_SOURCES as u32 {
+            assert!(limiter.allow(IpAddr::V4(Ipv4Addr::from(n))));
+        }
+        assert!(!limiter.allow(ALICE));
+        assert_eq!(MAX_TRACKED_SOURCES, limiter.sources.len());
+
+        // Once their buckets have refilled, the old sources are forgotten to make room.
+        clock.advance(PRUNE_INTERVAL);
+        assert!(limiter.allow(ALICE));
+        assert_eq!(1, limiter.sources.len());
+    }
+}
@@ -2,30 +2,68 @@
 
 #[cfg(test)]
 mod test {
-    use common::THREAD_SLOW_DOWN;
+    use echo::{
+        spawn_for_test, spawn_for_test_with_mode, spawn_for_test_with_service,
+        spawn_udp_for_test_with_limit, spawn_udp_for_test_with_service, udp::RateLimit, Mode,
+        Service, BUFFER_EXCEEDED_RESPONSE,
+    };
+    use std::io::{Read, Write};
+    use std::net::{Shutdown, TcpStream};
+    use std::sync::Arc;
     use std::thread;
-    use std::time::Duration;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
     use testing::{
-        assert_client_receives_bytes, connect, listen_on_available_port, send_bytes_from,
+        assert_client_receives_bytes,
+        clock::TestClock,
+        conformance::{assert_conforms, Problem},
+        connect,
+        fault::{FaultInjector, Fragment},
+        send_bytes_from, send_bytes_with_faults,
+        udp::UdpTestClient,
     };
 
     const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+    // Seconds between 1900 (the RFC 868 epoch) and 1970.
+    const TIME_EPOCH_OFFSET: u64 = 2_208_988_800;
 
-    fn setup() -> u16 {
-        let (listener, port) = listen_on_available_port();
-        thread::spawn(move || loop {
-            if let Ok((stream, _)) = listener.accept() {
-                thread::spawn(move || echo::handle_stream_immediate(stream));
-            }
-            thread::sleep(THREAD_SLOW_DOWN);
-        });
-        port
+    fn connect_blocking(port: u16) -> TcpStream {
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_read_timeout(Some(DEFAULT_TIMEOUT)).unwrap();
+        stream
+    }
+
+    fn chargen_lines(first: usize, count: usize) -> Vec<u8> {
+        (first..first + count)
+            .flat_map(|n| {
+                let mut line: Vec<u8> = (0..72).map(|i| b' ' + ((n + i) % 95) as u8).collect();
+                line.extend_from_slice(b"\r\n");
+                line
+            })
+            .collect()
+    }
+
+    fn assert_recent_time(response: &[u8]) {
+        let seconds = u32::from_be_bytes(response.try_into().expect("Four bytes")) as u64;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!((now + TIME_EPOCH_OFFSET).abs_diff(seconds) <= 2);
+    }
+
+    // Such as "Sunday, February 20, 2022 12:34:56-UTC\r\n".
+    fn assert_daytime(response: &[u8]) {
+        let response = String::from_utf8(response.to_vec()).expect("Text");
+        let (date, time) = response.rsplit_once(' ').expect("Date and time");
+        assert_eq!(2, date.matches(", ").count(), "{response:?}");
+        assert_eq!("HH:MM:SS-UTC\r\n".len(), time.len(), "{response:?}");
+        assert!(time.ends_with("-UTC\r\n"), "{response:?}");
     }
 
     #[test]
     fn echo_good_exact() {
-        let port = setup();
-        let mut client = connect(port);
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
 
         send_bytes_from!(client, "40 00 00 00 0a");
         assert_client_receives_bytes!(client, "40 00 00 00 0a", DEFAULT_TIMEOUT);
@@ -33,8 +71,8 @@ mod test {
 
     #[test]
     fn echo_good_extra() {
-        let port = setup();
-        let mut client = connect(port);
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
 
         send_bytes_from!(client, "40 00 00 00 0a");
         assert_client_receives_bytes!(client, "40 00", DEFAULT_TIMEOUT);
@@ -43,8 +81,8 @@ mod test {
     #[test]
     #[should_panic]
     fn echo_bad() {
-        let port = setup();
-        let mut client = connect(port);
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
 
         send_bytes_from!(client, "40 00 00 00 0a");
         assert_client_receives_bytes!(client, "40 00 12 00 0a", DEFAULT_TIMEOUT);
@@ -53,10 +91,274 @@ mod test {
     #[test]
     #[should_panic]
     fn echo_timeout() {
-        let port = setup();
-        let mut client = connect(port);
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
 
         send_bytes_from!(client, "40 00 00 00 0a");
         assert_client_receives_bytes!(client, "40 00 00 00 0a ab", DEFAULT_TIMEOUT);
     }
+
+    #[test]
+    fn echo_byte_by_byte() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+        let mut faults = FaultInjector::new(Fragment::ByteByByte);
+
+        send_bytes_with_faults!(faults, client, "40 00 00 00 0a 61 62 63");
+        assert_client_receives_bytes!(client, "40 00 00 00 0a 61 62 63", DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn echo_random_fragments_with_jitter() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+        let mut faults = FaultInjector::new(Fragment::Random).with_jitter(Duration::from_millis(2));
+
+        send_bytes_with_faults!(faults, client, "00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d");
+        assert_client_receives_bytes!(
+            client,
+            "00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d",
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn echo_after_half_close() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+        let mut faults = FaultInjector::new(Fragment::Whole);
+
+        send_bytes_with_faults!(faults, client, "68 65 6c 6c 6f");
+        faults.half_close(&mut client);
+        assert_client_receives_bytes!(client, "68 65 6c 6c 6f", DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn echo_keeps_reading_after_half_close() {
+        let server = spawn_for_test();
+        let mut client = connect_blocking(server.port);
+        let sent: Vec<u8> = (0..65_536).map(|i| (i % 251) as u8).collect();
+
+        let mut writer = client.try_clone().unwrap();
+        let expected = sent.clone();
+        let writing = thread::spawn(move || {
+            writer.write_all(&sent).unwrap();
+            writer.shutdown(Shutdown::Write).unwrap();
+        });
+        let mut received = vec![];
+        client.read_to_end(&mut received).unwrap();
+        writing.join().unwrap();
+        assert_eq!(expected, received);
+    }
+
+    #[test]
+    fn conformance() {
+        let server = spawn_for_test();
+        assert_conforms(Problem::Smoke, server.port);
+    }
+
+    #[test]
+    fn buffered_after_half_close() {
+        let server = spawn_for_test_with_mode(Mode::Buffered { limit: 8 });
+        let mut client = connect(server.port);
+        let mut faults = FaultInjector::new(Fragment::ByteByByte);
+
+        send_bytes_with_faults!(faults, client, "68 65 6c 6c 6f");
+        send_bytes_with_faults!(faults, client, "21 21 21");
+        faults.half_close(&mut client);
+        assert_client_receives_bytes!(client, "68 65 6c 6c 6f 21 21 21", DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    #[should_panic]
+    fn buffered_waits_for_half_close() {
+        let server = spawn_for_test_with_mode(Mode::Buffered { limit: 8 });
+        let mut client = connect(server.port);
+
+        send_bytes_from!(client, "68 65 6c 6c 6f");
+        assert_client_receives_bytes!(client, "68", DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn buffered_limit_exceeded() {
+        let server = spawn_for_test_with_mode(Mode::Buffered { limit: 8 });
+        let mut client = connect(server.port);
+
+        send_bytes_from!(client, "68 65 6c 6c 6f 21 21 21 21");
+        assert_client_receives_bytes!(
+            client,
+            &testing::u8s_to_hex_str(BUFFER_EXCEEDED_RESPONSE),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn buffered_sends_everything_before_closing() {
+        let server = spawn_for_test_with_mode(Mode::Buffered { limit: 65_536 });
+        let mut client = connect_blocking(server.port);
+        let sent: Vec<u8> = (0..65_536).map(|i| (i % 251) as u8).collect();
+
+        client.write_all(&sent).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let mut received = vec![];
+        client.read_to_end(&mut received).unwrap();
+        assert_eq!(sent, received);
+    }
+
+    #[test]
+    fn buffered_limit_exceeded_is_not_reset() {
+        let server = spawn_for_test_with_mode(Mode::Buffered { limit: 8 });
+        let mut client = connect_blocking(server.port);
+
+        // Keep sending after the limit: the response must still arrive, followed by a clean close.
+        client.write_all(&[b'!'; 65_536]).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let mut received = vec![];
+        client.read_to_end(&mut received).unwrap();
+        assert_eq!(BUFFER_EXCEEDED_RESPONSE, received);
+    }
+
+    #[test]
+    fn splice_keeps_reading_after_half_close() {
+        let server = spawn_for_test_with_mode(Mode::Splice);
+        let mut client = connect_blocking(server.port);
+        let sent: Vec<u8> = (0..1_048_576).map(|i| (i % 251) as u8).collect();
+
+        let mut writer = client.try_clone().unwrap();
+        let expected = sent.clone();
+        let writing = thread::spawn(move || {
+            writer.write_all(&sent).unwrap();
+            writer.shutdown(Shutdown::Write).unwrap();
+        });
+        let mut received = vec![];
+        client.read_to_end(&mut received).unwrap();
+        writing.join().unwrap();
+        assert_eq!(expected, received);
+    }
+
+    #[test]
+    fn immediate_is_not_limited() {
+        let server = spawn_for_test_with_mode(Mode::Immediate);
+        let mut client = connect(server.port);
+
+        send_bytes_from!(client, "68 65 6c 6c 6f 21 21 21 21");
+        assert_client_receives_bytes!(client, "68 65 6c 6c 6f 21 21 21 21", DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn discard_tcp() {
+        let server = spawn_for_test_with_service(Service::Discard, Mode::Immediate);
+        let mut client = connect_blocking(server.port);
+
+        _ = client.write_all(b"hello");
+        _ = client.shutdown(Shutdown::Write);
+        let mut response: Vec<u8> = vec![];
+        _ = client.read_to_end(&mut response);
+        assert!(response.is_empty());
+    }
+
+    #[test]
+    fn chargen_tcp() {
+        let server = spawn_for_test_with_service(Service::Chargen, Mode::Immediate);
+        let mut client = connect_blocking(server.port);
+
+        let expected = chargen_lines(0, 100);
+        let mut response = vec![0u8; expected.len()];
+        client.read_exact(&mut response).unwrap();
+        assert_eq!(expected, response);
+    }
+
+    #[test]
+    fn daytime_tcp() {
+        let server = spawn_for_test_with_service(Service::Daytime, Mode::Immediate);
+        let mut client = connect_blocking(server.port);
+
+        let mut response: Vec<u8> = vec![];
+        client.read_to_end(&mut response).unwrap();
+        assert_daytime(&response);
+    }
+
+    #[test]
+    fn time_tcp() {
+        let server = spawn_for_test_with_service(Service::Time, Mode::Immediate);
+        let mut client = connect_blocking(server.port);
+
+        let mut response: Vec<u8> = vec![];
+        client.read_to_end(&mut response).unwrap();
+        assert_recent_time(&response);
+    }
+
+    #[test]
+    fn discard_udp() {
+        let server = spawn_udp_for_test_with_service(Service::Discard);
+        let mut client = UdpTestClient::connect(server.port);
+
+        client.send(b"hello");
+        client.expect_no_reply(DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn chargen_udp() {
+        let server = spawn_udp_for_test_with_service(Service::Chargen);
+        let mut client = UdpTestClient::connect(server.port);
+
+        // Each reply carries on the pattern from the last.
+        client.send(b"");
+        client.expect_datagram(&chargen_lines(0, 6), DEFAULT_TIMEOUT);
+        client.send(b"");
+        client.expect_datagram(&chargen_lines(6, 6), DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn daytime_udp() {
+        let server = spawn_udp_for_test_with_service(Service::Daytime);
+        let mut client = UdpTestClient::connect(server.port);
+
+        client.send(b"");
+        assert_daytime(&client.receive(DEFAULT_TIMEOUT).expect("Daytime reply"));
+    }
+
+    #[test]
+    fn time_udp() {
+        let server = spawn_udp_for_test_with_service(Service::Time);
+        let mut client = UdpTestClient::connect(server.port);
+
+        client.send(b"");
+        assert_recent_time(&client.receive(DEFAULT_TIMEOUT).expect("Time reply"));
+    }
+
+    #[test]
+    fn echo_udp() {
+        let server = spawn_udp_for_test_with_service(Service::Echo);
+        let mut client = UdpTestClient::connect(server.port);
+
+        client.send(b"hello");
+        client.expect_datagram(b"hello", DEFAULT_TIMEOUT);
+        // Larger than the TCP read buffer, but still one datagram.
+        let payload: Vec<u8> = (0..8_000).map(|i| i as u8).collect();
+        client.send(&payload);
+        client.expect_datagram(&payload, DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn echo_udp_rate_limited() {
+        let clock = Arc::new(TestClock::new());
+        let limit = RateLimit {
+            burst: 2,
+            per_second: 1,
+        };
+        let server = spawn_udp_for_test_with_limit(Service::Echo, Some(limit), clock.clone());
+        let mut client = UdpTestClient::connect(server.port);
+
+        client.send(b"one");
+        client.expect_datagram(b"one", DEFAULT_TIMEOUT);
+        client.send(b"two");
+        client.expect_datagram(b"two", DEFAULT_TIMEOUT);
+        client.send(b"three");
+        client.expect_no_reply(DEFAULT_TIMEOUT);
+
+        clock.advance(Duration::from_secs(1));
+        client.send(b"four");
+        client.expect_datagram(b"four", DEFAULT_TIMEOUT);
+    }
 }
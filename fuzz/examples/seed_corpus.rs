@@ -0,0 +1,125 @@
+//! Write seed corpora for every fuzz target into `corpus/<target>/`, taken from the inputs used by
+//! the unit, integration and conformance tests, and every client's stream in `speed/speed.log`.
+//!
+//! Run from the `fuzz` directory: `cargo run --example seed_corpus`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use testing::trace::{Event, Trace};
+
+const SPEED_LOG: &str = include_str!("../../speed/speed.log");
+
+type Seeds = fn() -> Vec<Vec<u8>>;
+
+fn speed_parser() -> Vec<Vec<u8>> {
+    let mut seeds: Vec<Vec<u8>> = vec![
+        vec![0x80, 0x03, 0x11, 0x0c, 0x9d, 0x00, 0x64],
+        vec![0x81, 0x02, 0x03, 0x11, 0xab, 0x9d],
+        vec![0x20, 0x04, b'U', b'N', b'1', b'X', 0x00, 0x00, 0x03, 0xe8],
+        vec![0x40, 0x00, 0x00, 0x00, 0x0a],
+        vec![0x00],
+    ];
+    let trace = Trace::parse(SPEED_LOG).expect("speed.log should be a valid trace");
+    let mut streams: HashMap<String, Vec<u8>> = HashMap::new();
+    for event in trace.events {
+        if let Event::Send { client, bytes } = event {
+            streams.entry(client).or_default().extend(bytes);
+        }
+    }
+    seeds.extend(streams.into_values());
+    seeds
+}
+
+fn primes_json() -> Vec<Vec<u8>> {
+    [
+        "{\"method\":\"isPrime\",\"number\":7}\n",
+        "{\"method\":\"isPrime\",\"number\":8.5}\n",
+        "{\"method\":\"isPrime\",\"number\":-3}\n",
+        "{\"method\":\"isPrime\",\"number\":18446744073709551617}\n",
+        "{\"number\":13,\"method\":\"isPrime\",\"extra\":[1,2,3]}\n",
+        "{\"method\":\"isPrime\",\"number\":7\n",
+        "{\"method\":\"isPrime\"}\n",
+        "{\"method\":\"isPrim\",\"number\":7}\n",
+        "{\"method\":\"isPrime\",\"number\":\"7\"}\n",
+        "{\"method\":\"nextPrime\",\"number\":18446744073709551616}\n",
+        "{\"method\":\"previousPrime\",\"number\":2}\n",
+        "{\"method\":\"factorize\",\"number\":360}\n",
+        "{\"method\":\"isProbablePrime\",\"number\":7,\"rounds\":10}\n",
+        "{\"method\":\"primeCount\",\"number\":1000}\n",
+    ]
+    .iter()
+    .map(|seed| seed.as_bytes().to_vec())
+    .collect()
+}
+
+fn keystore_framing() -> Vec<Vec<u8>> {
+    // The example session from the specification.
+    let messages: [(u8, i32, i32); 5] = [
+        (b'I', 12345, 101),
+        (b'I', 12346, 102),
+        (b'I', 12347, 100),
+        (b'I', 40960, 5),
+        (b'Q', 12288, 16384),
+    ];
+    let session = messages
+        .iter()
+        .flat_map(|(kind, first, second)| {
+            let mut message = vec![*kind];
+            message.extend_from_slice(&first.to_be_bytes());
+            message.extend_from_slice(&second.to_be_bytes());
+            message
+        })
+        .collect::<Vec<u8>>();
+    vec![session, b"X\0\0\0\0\0\0\0\0".to_vec()]
+}
+
+fn db_request() -> Vec<Vec<u8>> {
+    ["foo=bar", "foo", "foo=bar=baz", "foo=", "=foo", "=", "version", "version=hacked", ""]
+        .iter()
+        .map(|seed| seed.as_bytes().to_vec())
+        .collect()
+}
+
+fn chat_name() -> Vec<Vec<u8>> {
+    let mut seeds: Vec<Vec<u8>> = ["alice", "Bob42", "", "al ice", "alice!", "ålice"]
+        .iter()
+        .map(|seed| seed.as_bytes().to_vec())
+        .collect();
+    seeds.push(vec![0xff, 0xfe, b'a']);
+    seeds
+}
+
+fn mob_spoofer() -> Vec<Vec<u8>> {
+    [
+        "Hi alice, please send payment to 7F1u3wSD5RbOHQmupo9nx4TnhQ\n",
+        "7iKDZEwPZSqIvDnHvVN2r0hUWXD5rHX\n",
+        "Either 7LOrwbDlS8NujgjddyogWgIM93MV5N2VR or 7adNeSwJkMakpEcln9HEtthSRtxdmEHOT8T\n",
+        "7abcdefghijklmnopqrstuvwxyz0123456789\n",
+        "This is a product ID, not a Boguscoin: 7YrZXzfUn5TtjGpPDQ4gV4BYNbuc-1234\n",
+    ]
+    .iter()
+    .map(|seed| seed.as_bytes().to_vec())
+    .collect()
+}
+
+fn main() {
+    let targets: [(&str, Seeds); 6] = [
+        ("speed_parser", speed_parser),
+        ("primes_json", primes_json),
+        ("keystore_framing", keystore_framing),
+        ("db_request", db_request),
+        ("chat_name", chat_name),
+        ("mob_spoofer", mob_spoofer),
+    ];
+    for (target, seeds) in targets {
+        let directory = Path::new("corpus").join(target);
+        fs::create_dir_all(&directory).expect("Could not create corpus directory.");
+        let seeds = seeds();
+        for (index, seed) in seeds.iter().enumerate() {
+            fs::write(directory.join(format!("seed-{index:03}")), seed)
+                .expect("Could not write seed.");
+        }
+        println!("{target}: {} seeds", seeds.len());
+    }
+}
@@ -0,0 +1,41 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use keystore::{bench::Store, spawn_for_test};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+fn hot_paths(c: &mut Criterion) {
+    let mut group = c.benchmark_group("keystore/handle_query");
+    for size in [100i32, 10_000, 200_000] {
+        let store = Store::new((0..size).map(|timestamp| (timestamp, timestamp % 1_000)));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &store, |b, store| {
+            b.iter(|| store.query(black_box(size / 4), black_box(size / 2)))
+        });
+    }
+    group.finish();
+}
+
+/// Round trips through `common::serve` (as used by `common::run`) over a real TCP connection.
+fn loopback(c: &mut Criterion) {
+    let server = spawn_for_test();
+    let mut stream = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+    stream.set_nodelay(true).unwrap();
+    let mut timestamp = 0i32;
+    let mut response = [0u8; 4];
+
+    c.bench_function("keystore/loopback", |b| {
+        b.iter(|| {
+            timestamp += 1;
+            let mut messages: Vec<u8> = vec![b'I'];
+            messages.extend_from_slice(&timestamp.to_be_bytes());
+            messages.extend_from_slice(&100i32.to_be_bytes());
+            messages.push(b'Q');
+            messages.extend_from_slice(&0i32.to_be_bytes());
+            messages.extend_from_slice(&timestamp.to_be_bytes());
+            stream.write_all(&messages).unwrap();
+            stream.read_exact(&mut response).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, hot_paths, loopback);
+criterion_main!(benches);
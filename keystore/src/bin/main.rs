@@ -0,0 +1,6 @@
+use common::run;
+use keystore::handle_stream;
+
+fn main() {
+    run(handle_stream, None, false);
+}
@@ -0,0 +1,257 @@
+use common::{serve, spawn_tcp_for_test, ServerHandle, BUFFER_SIZE};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::vec::Drain;
+
+const MESSAGE_LENGTH: usize = 9;
+
+struct AssetPrice {
+    timestamp: i32,
+    price: i32,
+}
+
+#[derive(Debug, PartialEq)]
+enum Message {
+    Insert { timestamp: i32, price: i32 },
+    Query { min: i32, max: i32 },
+}
+
+fn to_i32(input: &[u8]) -> i32 {
+    let (bytes, _) = input.split_at(std::mem::size_of::<i32>());
+    i32::from_be_bytes(bytes.try_into().unwrap())
+}
+
+/// Take the next complete message off the front of the queue, if there is one. Messages of an
+/// unknown type are an error.
+fn next_message(queue: &mut Vec<u8>) -> Option<Result<Message, ()>> {
+    if queue.len() < MESSAGE_LENGTH {
+        return None;
+    }
+    let message: Drain<u8> = queue.drain(0..MESSAGE_LENGTH);
+    let bytes: &[u8] = message.as_slice();
+    let (first, second) = (to_i32(&bytes[1..5]), to_i32(&bytes[5..9]));
+    Some(match &bytes[0] {
+        73 | 105 => Ok(Message::Insert {
+            timestamp: first,
+            price: second,
+        }),
+        81 | 113 => Ok(Message::Query {
+            min: first,
+            max: second,
+        }),
+        _ => Err(()),
+    })
+}
+
+pub fn handle_stream(mut stream: TcpStream) {
+    let mut store: Vec<AssetPrice> = vec![];
+    let mut queue: Vec<u8> = vec![];
+    let mut input: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
+
+    'connected: loop {
+        match stream.read(&mut input) {
+            Ok(0) => break 'connected,
+            Ok(length) => queue.extend_from_slice(&input[..length]),
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => (),
+            Err(err) => panic!("Error processing stream: {err:?}"),
+        };
+
+        while let Some(message) = next_message(&mut queue) {
+            match message {
+                Ok(Message::Insert { timestamp, price }) => {
+                    store.push(AssetPrice { timestamp, price })
+                }
+                Ok(Message::Query { min, max }) => {
+                    _ = stream.write_all(&handle_query(min, max, &store).to_be_bytes())
+                }
+                Err(()) => break 'connected,
+            }
+        }
+    }
+
+    stream.shutdown(Shutdown::Both).unwrap_or_default();
+}
+
+fn handle_query(min: i32, max: i32, store: &[AssetPrice]) -> i32 {
+    let prices_within_daterange = store
+        .iter()
+        .filter(|asset: &&AssetPrice| -> bool { asset.timestamp >= min && asset.timestamp <= max })
+        .map(|asset: &AssetPrice| -> i32 { asset.price.to_owned() });
+    let mut count: usize = 0;
+    let mut total: i128 = 0;
+    for price in prices_within_daterange {
+        // Too lazy to figure out how to satisfy the trait for .sum();
+        count += 1;
+        total += price as i128;
+    }
+    let average = match count {
+        0 => 0,
+        count => total / (count as i128),
+    };
+    average as i32
+}
+
+pub fn spawn_for_test() -> ServerHandle {
+    spawn_tcp_for_test(|listener, shutdown| serve(listener, handle_stream, false, &shutdown))
+}
+
+/// Session stores and queries, for `benches/keystore.rs`.
+#[cfg(feature = "bench")]
+pub mod bench {
+    use super::{handle_query, AssetPrice};
+
+    /// Prices inserted during a single session.
+    pub struct Store(Vec<AssetPrice>);
+    impl Store {
+        pub fn new(prices: impl IntoIterator<Item = (i32, i32)>) -> Self {
+            Self(
+                prices
+                    .into_iter()
+                    .map(|(timestamp, price)| AssetPrice { timestamp, price })
+                    .collect(),
+            )
+        }
+
+        pub fn query(&self, min: i32, max: i32) -> i32 {
+            handle_query(min, max, &self.0)
+        }
+    }
+}
+
+/// Message framing and queries, for the `keystore_framing` fuzz target.
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing {
+    use super::{handle_query, next_message, AssetPrice, Message, MESSAGE_LENGTH};
+
+    /// Frame and handle a session's messages the way `handle_stream` does, without a socket.
+    pub fn framing(data: &[u8]) {
+        let mut store: Vec<AssetPrice> = vec![];
+        let mut queue: Vec<u8> = data.to_vec();
+        while let Some(message) = next_message(&mut queue) {
+            match message {
+                Ok(Message::Insert { timestamp, price }) => {
+                    store.push(AssetPrice { timestamp, price })
+                }
+                Ok(Message::Query { min, max }) => _ = handle_query(min, max, &store),
+                Err(()) => return,
+            }
+        }
+        assert!(queue.len() < MESSAGE_LENGTH);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{handle_query, next_message, to_i32, AssetPrice, Message, MESSAGE_LENGTH};
+    use proptest::prelude::*;
+
+    fn encode(message: &Message) -> Vec<u8> {
+        let (kind, first, second) = match message {
+            Message::Insert { timestamp, price } => (b'I', timestamp, price),
+            Message::Query { min, max } => (b'Q', min, max),
+        };
+        let mut bytes = vec![kind];
+        bytes.extend_from_slice(&first.to_be_bytes());
+        bytes.extend_from_slice(&second.to_be_bytes());
+        bytes
+    }
+
+    fn message() -> impl Strategy<Value = Message> {
+        prop_oneof![
+            (any::<i32>(), any::<i32>())
+                .prop_map(|(timestamp, price)| Message::Insert { timestamp, price }),
+            (any::<i32>(), any::<i32>()).prop_map(|(min, max)| Message::Query { min, max }),
+        ]
+    }
+
+    #[test]
+    fn test_lowercase_types() {
+        let mut queue = b"i\0\0\0\x01\0\0\0\x02q\0\0\0\x01\0\0\0\x02".to_vec();
+        assert_eq!(
+            Some(Ok(Message::Insert {
+                timestamp: 1,
+                price: 2
+            })),
+            next_message(&mut queue)
+        );
+        assert_eq!(
+            Some(Ok(Message::Query { min: 1, max: 2 })),
+            next_message(&mut queue)
+        );
+        assert_eq!(None, next_message(&mut queue));
+    }
+
+    #[test]
+    fn test_mean_does_not_overflow() {
+        let store: Vec<AssetPrice> = [i32::MAX; 3]
+            .into_iter()
+            .map(|price| AssetPrice {
+                timestamp: 0,
+                price,
+            })
+            .collect();
+        assert_eq!(i32::MAX, handle_query(0, 0, &store));
+    }
+
+    proptest! {
+        #[test]
+        fn test_to_i32(value in any::<i32>(), trailing in prop::collection::vec(any::<u8>(), 0..8)) {
+            let mut bytes = value.to_be_bytes().to_vec();
+            bytes.extend_from_slice(&trailing);
+            prop_assert_eq!(value, to_i32(&bytes));
+        }
+
+        #[test]
+        fn test_round_trip(message in message()) {
+            let mut queue = encode(&message);
+            prop_assert_eq!(Some(Ok(message)), next_message(&mut queue));
+            prop_assert!(queue.is_empty());
+        }
+
+        #[test]
+        fn test_split_anywhere(
+            messages in prop::collection::vec(message(), 1..16),
+            chunk_size in 1usize..32,
+        ) {
+            let bytes: Vec<u8> = messages.iter().flat_map(encode).collect();
+            let mut queue: Vec<u8> = vec![];
+            let mut parsed: Vec<Message> = vec![];
+            for chunk in bytes.chunks(chunk_size) {
+                queue.extend_from_slice(chunk);
+                while let Some(message) = next_message(&mut queue) {
+                    parsed.push(message.expect("Valid message"));
+                }
+                prop_assert!(queue.len() < MESSAGE_LENGTH);
+            }
+            prop_assert!(queue.is_empty());
+            prop_assert_eq!(messages, parsed);
+        }
+
+        #[test]
+        fn test_garbage_does_not_panic(mut queue in prop::collection::vec(any::<u8>(), 0..256)) {
+            let length = queue.len();
+            let mut consumed = 0;
+            while let Some(message) = next_message(&mut queue) {
+                consumed += MESSAGE_LENGTH;
+                if let Err(()) = message {
+                    break;
+                }
+            }
+            prop_assert_eq!(length - consumed, queue.len());
+        }
+
+        #[test]
+        fn test_mean_within_range(
+            prices in prop::collection::vec(any::<i32>(), 1..64),
+        ) {
+            let store: Vec<AssetPrice> = prices
+                .iter()
+                .enumerate()
+                .map(|(timestamp, &price)| AssetPrice { timestamp: timestamp as i32, price })
+                .collect();
+            let mean = handle_query(i32::MIN, i32::MAX, &store);
+            prop_assert!(*prices.iter().min().unwrap() <= mean);
+            prop_assert!(mean <= *prices.iter().max().unwrap());
+        }
+    }
+}
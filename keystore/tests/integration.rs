@@ -0,0 +1,54 @@
+//! Integration tests for Means to an End.
+
+#[cfg(test)]
+mod test {
+    use keystore::spawn_for_test;
+    use std::time::Duration;
+    use testing::{
+        assert_client_receives_bytes,
+        conformance::{assert_conforms, Problem},
+        connect, send_bytes_from,
+    };
+
+    const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+    #[test]
+    fn example_session() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+
+        send_bytes_from!(client, "49 00 00 30 39 00 00 00 65");
+        send_bytes_from!(client, "49 00 00 30 3a 00 00 00 66");
+        send_bytes_from!(client, "49 00 00 30 3b 00 00 00 64");
+        send_bytes_from!(client, "49 00 00 a0 00 00 00 00 05");
+        send_bytes_from!(client, "51 00 00 30 00 00 00 40 00");
+        assert_client_receives_bytes!(client, "00 00 00 65", DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn empty_range() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+
+        send_bytes_from!(client, "49 00 00 30 39 00 00 00 65");
+        send_bytes_from!(client, "51 00 00 40 00 00 00 30 00");
+        assert_client_receives_bytes!(client, "00 00 00 00", DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn sessions_are_separate() {
+        let server = spawn_for_test();
+        let mut first = connect(server.port);
+        let mut second = connect(server.port);
+
+        send_bytes_from!(first, "49 00 00 30 39 00 00 00 65");
+        send_bytes_from!(second, "51 00 00 30 00 00 00 40 00");
+        assert_client_receives_bytes!(second, "00 00 00 00", DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn conformance() {
+        let server = spawn_for_test();
+        assert_conforms(Problem::MeansToAnEnd, server.port);
+    }
+}
@@ -0,0 +1,59 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use mob::{bench::spoofer, spawn_for_test_with_upstream};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use testing::upstream::MockChat;
+
+fn replace(c: &mut Criterion) {
+    let replace = spoofer();
+    let mut group = c.benchmark_group("mob/replace");
+    let messages: [(&str, &[u8]); 3] = [
+        ("no addresses", b"[alice] Hi bob, how are you doing today?\n"),
+        ("one address", b"[alice] Send it to 7F1u3wSD5RbOHQmupo9nx4TnhQ please\n"),
+        (
+            "many addresses",
+            b"7iKDZEwPZSqIvDnHvVN2r0hUWXD5rHX 7LOrwbDlS8NujgjddyogWgIM93MV5N2VR 7adNeSwJkMakpEcln9HEtthSRtxdmEHOT8T\n",
+        ),
+    ];
+    for (name, message) in messages {
+        group.throughput(Throughput::Bytes(message.len() as u64));
+        group.bench_function(name, |b| b.iter(|| replace(black_box(message))));
+    }
+    group.finish();
+}
+
+/// Messages relayed end to end over real TCP connections: from a client of the proxy (through its
+/// own accept loop) to a local chat server, and on to another client connected to that directly.
+fn loopback(c: &mut Criterion) {
+    let upstream = MockChat::spawn();
+    let proxy = spawn_for_test_with_upstream(upstream.address());
+    let join = |port: u16, name: &str, lines: usize| {
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_nodelay(true).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        writer.write_all(format!("{name}\n").as_bytes()).unwrap();
+        for _ in 0..lines {
+            reader.read_line(&mut line).unwrap();
+        }
+        (writer, reader)
+    };
+    let (mut victim, _victim) = join(proxy.port, "victim", 1);
+    let (_direct, mut direct) = join(upstream.port(), "direct", 1);
+    let mut line = String::new();
+
+    c.bench_function("mob/loopback", |b| {
+        b.iter(|| {
+            victim
+                .write_all(b"Send it to 7F1u3wSD5RbOHQmupo9nx4TnhQ please\n")
+                .unwrap();
+            line.clear();
+            direct.read_line(&mut line).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, replace, loopback);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+use common::{get_tcp_listener, ShutdownSignal};
+use std::env;
+
+// Usage: mob [port] [upstream address]
+fn main() {
+    let listener = get_tcp_listener(None);
+    let upstream = env::args()
+        .nth(2)
+        .unwrap_or_else(|| mob::DEFAULT_UPSTREAM.to_string());
+    println!("Proxying to {upstream}...");
+    mob::serve(listener, &upstream, &ShutdownSignal::new());
+}
@@ -1,12 +1,12 @@
-use common::{get_tcp_listener, BUFFER_SIZE, THREAD_SLOW_DOWN};
+use common::{spawn_tcp_for_test, ServerHandle, ShutdownSignal, BUFFER_SIZE, THREAD_SLOW_DOWN};
 use core::panic;
 use regex::bytes::Regex;
 use std::borrow::Cow;
 use std::io::{ErrorKind, Read, Write};
-use std::net::{Shutdown, TcpStream};
+use std::net::{Shutdown, TcpListener, TcpStream};
 use std::thread;
 
-const UPSTREAM_SERVER: &str = "chat.protohackers.com:16963";
+pub const DEFAULT_UPSTREAM: &str = "chat.protohackers.com:16963";
 const BOGUSCOIN_MATCHER: &str = "^7[a-zA-Z0-9]{25,34}$";
 const TONY_BOGUSCOIN_ADDRESS: &[u8] = b"7YWHMfk9JZe0LM0g1ZauHuiSxhI";
 
@@ -14,11 +14,11 @@ struct Spoofer {
     re: Regex,
 }
 
-fn main() {
-    let listener = get_tcp_listener(None);
-    loop {
+/// Proxy every connection to the budget chat server at `upstream` (such as `DEFAULT_UPSTREAM`).
+pub fn serve(listener: TcpListener, upstream: &str, shutdown: &ShutdownSignal) {
+    while !shutdown.is_triggered() {
         if let Ok((victim, _)) = listener.accept() {
-            let upstream: TcpStream = match TcpStream::connect(UPSTREAM_SERVER) {
+            let upstream: TcpStream = match TcpStream::connect(upstream) {
                 Ok(stream) => stream,
                 Err(_) => {
                     _ = victim.shutdown(Shutdown::Both);
@@ -42,6 +42,14 @@ fn main() {
     }
 }
 
+pub fn spawn_for_test() -> ServerHandle {
+    spawn_for_test_with_upstream(DEFAULT_UPSTREAM.to_string())
+}
+
+pub fn spawn_for_test_with_upstream(upstream: String) -> ServerHandle {
+    spawn_tcp_for_test(move |listener, shutdown| serve(listener, &upstream, &shutdown))
+}
+
 fn handle_stream(mut upstream: TcpStream, mut downstream: TcpStream) {
     let spoofer = Spoofer::new();
     let mut buffer = [0u8; BUFFER_SIZE];
@@ -96,3 +104,29 @@ impl Spoofer {
         lines.join(&b'\n')
     }
 }
+
+/// The address spoofer, for `benches/mob.rs`.
+#[cfg(feature = "bench")]
+pub mod bench {
+    /// Rewrite any Boguscoin addresses in the buffer.
+    pub fn spoofer() -> impl Fn(&[u8]) -> Vec<u8> {
+        let spoofer = super::Spoofer::new();
+        move |buffer| spoofer.replace(buffer)
+    }
+}
+
+/// Address rewriting, for the `mob_spoofer` fuzz target.
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing {
+    use super::Spoofer;
+    use std::sync::OnceLock;
+
+    /// Rewriting addresses must never add or remove lines or words.
+    pub fn replace(data: &[u8]) {
+        static SPOOFER: OnceLock<Spoofer> = OnceLock::new();
+        let spoofed = SPOOFER.get_or_init(Spoofer::new).replace(data);
+        let count = |bytes: &[u8], separator: u8| bytes.iter().filter(|&&b| b == separator).count();
+        assert_eq!(count(data, b'\n'), count(&spoofed, b'\n'));
+        assert_eq!(count(data, b' '), count(&spoofed, b' '));
+    }
+}
@@ -0,0 +1,100 @@
+//! Integration tests for Mob in the Middle, proxying to a local budget chat server.
+
+#[cfg(test)]
+mod test {
+    use mob::spawn_for_test_with_upstream;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::time::Duration;
+    use testing::{
+        assert_client_receives_bytes,
+        conformance::{assert_conforms, Problem},
+        connect,
+        upstream::MockChat,
+    };
+
+    const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+    const WELCOME: &str = "Welcome to budgetchat! What shall I call you?\n";
+    const TONY: &str = "7YWHMfk9JZe0LM0g1ZauHuiSxhI";
+
+    fn hex(text: &str) -> String {
+        testing::u8s_to_hex_str(text.as_bytes())
+    }
+
+    fn join(port: u16, name: &str, room: &str) -> TcpStream {
+        let mut client = connect(port);
+        assert_client_receives_bytes!(client, &hex(WELCOME), DEFAULT_TIMEOUT);
+        _ = client.write_all(format!("{name}\n").as_bytes());
+        assert_client_receives_bytes!(
+            client,
+            &hex(&format!("* The room contains: {room}\n")),
+            DEFAULT_TIMEOUT
+        );
+        client
+    }
+
+    /// The victim joins through the proxy, and the other user joins the chat server directly.
+    fn victim_and_direct(upstream: &MockChat, proxy: u16) -> (TcpStream, TcpStream) {
+        let mut victim = join(proxy, "victim", "");
+        let direct = join(upstream.port(), "direct", "victim");
+        assert_client_receives_bytes!(
+            victim,
+            &hex("* direct has entered the room\n"),
+            DEFAULT_TIMEOUT
+        );
+        (victim, direct)
+    }
+
+    #[test]
+    fn rewrites_messages_sent_upstream() {
+        let upstream = MockChat::spawn();
+        let proxy = spawn_for_test_with_upstream(upstream.address());
+        let (mut victim, mut direct) = victim_and_direct(&upstream, proxy.port);
+
+        _ = victim.write_all(b"Send it to 7F1u3wSD5RbOHQmupo9nx4TnhQ please\n");
+        assert_client_receives_bytes!(
+            direct,
+            &hex(&format!("[victim] Send it to {TONY} please\n")),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn rewrites_messages_sent_downstream() {
+        let upstream = MockChat::spawn();
+        let proxy = spawn_for_test_with_upstream(upstream.address());
+        let (mut victim, mut direct) = victim_and_direct(&upstream, proxy.port);
+
+        _ = direct
+            .write_all(b"7iKDZEwPZSqIvDnHvVN2r0hUWXD5rHX 7LOrwbDlS8NujgjddyogWgIM93MV5N2VR\n");
+        assert_client_receives_bytes!(
+            victim,
+            &hex(&format!("[direct] {TONY} {TONY}\n")),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn leaves_non_addresses_alone() {
+        let upstream = MockChat::spawn();
+        let proxy = spawn_for_test_with_upstream(upstream.address());
+        let (mut victim, mut direct) = victim_and_direct(&upstream, proxy.port);
+
+        // Too short, too long, and part of a larger word.
+        let message =
+            "7abc 7abcdefghijklmnopqrstuvwxyz0123456789 7YrZXzfUn5TtjGpPDQ4gV4BYNbuc-1234\n";
+        _ = victim.write_all(message.as_bytes());
+        assert_client_receives_bytes!(
+            direct,
+            &hex(&format!("[victim] {message}")),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn conformance() {
+        let upstream = MockChat::spawn();
+        let proxy = spawn_for_test_with_upstream(upstream.address());
+        assert_conforms(Problem::MobInTheMiddle, proxy.port);
+    }
+}
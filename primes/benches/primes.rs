@@ -0,0 +1,44 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use primes::{bench::process_json, spawn_for_test};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+
+fn hot_paths(c: &mut Criterion) {
+    let mut group = c.benchmark_group("primes/process_json");
+    let requests: [(&str, &[u8]); 4] = [
+        ("small prime", b"{\"method\":\"isPrime\",\"number\":7}\n"),
+        (
+            "large prime",
+            b"{\"method\":\"isPrime\",\"number\":2147483647}\n",
+        ),
+        ("fraction", b"{\"method\":\"isPrime\",\"number\":8.5}\n"),
+        ("malformed", b"{\"method\":\"isPrime\"}\n"),
+    ];
+    for (name, request) in requests {
+        group.bench_function(name, |b| b.iter(|| process_json(black_box(request))));
+    }
+    group.finish();
+}
+
+/// Round trips through `common::serve` (as used by `common::run`) over a real TCP connection.
+fn loopback(c: &mut Criterion) {
+    let server = spawn_for_test();
+    let stream = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+    stream.set_nodelay(true).unwrap();
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut response = String::new();
+
+    c.bench_function("primes/loopback", |b| {
+        b.iter(|| {
+            writer
+                .write_all(b"{\"method\":\"isPrime\",\"number\":2147483647}\n")
+                .unwrap();
+            response.clear();
+            reader.read_line(&mut response).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, hot_paths, loopback);
+criterion_main!(benches);
@@ -0,0 +1,48 @@
+use common::{run, DEFAULT_PORT};
+use primes::{Options, Protocol};
+use std::env;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: primes [port] [--protocol spec|jsonrpc] [--errors spec|descriptive] \
+                     [--sieve-limit <number>] [--cache-size <results>] [--workers <threads>] \
+                     [--max-line-length <bytes>] [--deadline-ms <milliseconds, 0 for none>] \
+                     [--binary] [--profile lenient|strict]";
+
+fn main() {
+    let mut port = DEFAULT_PORT;
+    let mut options = Options::default();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--protocol" => options.protocol = args.next().expect(USAGE).parse().expect(USAGE),
+            "--errors" => options.errors = args.next().expect(USAGE).parse().expect(USAGE),
+            "--sieve-limit" => {
+                options.sieve_limit = args.next().expect(USAGE).parse().expect(USAGE)
+            }
+            "--cache-size" => options.cache_size = args.next().expect(USAGE).parse().expect(USAGE),
+            "--workers" => options.workers = args.next().expect(USAGE).parse().expect(USAGE),
+            "--max-line-length" => {
+                options.max_line_length = args.next().expect(USAGE).parse().expect(USAGE)
+            }
+            "--deadline-ms" => {
+                let milliseconds: u64 = args.next().expect(USAGE).parse().expect(USAGE);
+                options.deadline = match milliseconds {
+                    0 => None,
+                    _ => Some(Duration::from_millis(milliseconds)),
+                };
+            }
+            "--binary" => options.binary = true,
+            "--profile" => options.profile = args.next().expect(USAGE).parse().expect(USAGE),
+            _ => port = arg.parse().expect(USAGE),
+        }
+    }
+
+    // Binary frames are answered as the specification's requests, never as JSON-RPC.
+    if options.binary && options.protocol == Protocol::JsonRpc {
+        panic!("{USAGE}");
+    }
+
+    println!("Serving primes with {options:?}...");
+    run(options.handler(), Some(port), false);
+}
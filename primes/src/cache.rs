@@ -0,0 +1,157 @@
+//! Answers shared by every connection: a sieve built once up to a limit, and the most recent
+//! primality results for numbers above it.
+
+use crate::deadline::{Deadline, Expired};
+use crate::primality;
+use crate::sieve::Sieve;
+use num_bigint::{BigInt, Sign};
+use num_traits::ToPrimitive;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+pub const DEFAULT_SIEVE_LIMIT: u64 = 1_000_000;
+pub const DEFAULT_CACHE_SIZE: usize = 65_536;
+// Larger numbers are not remembered, so that a full cache holds at most a few megabytes of them.
+const MAX_CACHED_BITS: u64 = 512;
+
+pub struct Cache {
+    sieve: Sieve,
+    results: Mutex<Results>,
+}
+impl Cache {
+    /// Sieve up to `sieve_limit` now, and remember up to `size` results above it.
+    pub fn new(sieve_limit: u64, size: usize) -> Self {
+        Self {
+            sieve: Sieve::new(sieve_limit),
+            results: Mutex::new(Results {
+                size,
+                ..Results::default()
+            }),
+        }
+    }
+
+    /// Whether `number` is prime, unless testing it runs past the deadline (and then nothing is
+    /// remembered about it).
+    pub fn is_prime(&self, number: &BigInt, deadline: &Deadline) -> Result<bool, Expired> {
+        if number.sign() == Sign::Minus {
+            return Ok(false);
+        }
+        match number.to_u64() {
+            Some(small) if small <= self.sieve.limit() => return Ok(self.sieve.is_prime(small)),
+            _ => (),
+        }
+        if let Some(&prime) = self.lock().get(number) {
+            return Ok(prime);
+        }
+        // Not holding the lock while testing, which can take a while for large numbers.
+        let prime = primality::is_prime(number, deadline)?;
+        self.lock().insert(number.clone(), prime);
+        Ok(prime)
+    }
+
+    /// Whether `is_prime` answers for `number` without testing it at length: it is negative, in
+    /// the sieve or remembered, or small enough to test exactly in a few steps.
+    pub fn knows(&self, number: &BigInt) -> bool {
+        number.sign() == Sign::Minus
+            || number.to_u64().is_some()
+            || self.lock().get(number).is_some()
+    }
+
+    /// How many primes there are up to and including `n`, sieving again beyond the limit.
+    pub fn prime_count(&self, n: u64) -> u64 {
+        match n <= self.sieve.limit() {
+            true => self.sieve.count(n),
+            false => Sieve::new(n).count(n),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Results> {
+        self.results.lock().expect("Primes cache poisoned.")
+    }
+}
+
+/// Results in the order they were added, so that the oldest are forgotten first.
+#[derive(Default)]
+struct Results {
+    size: usize,
+    primes: HashMap<BigInt, bool>,
+    order: VecDeque<BigInt>,
+}
+impl Results {
+    fn get(&self, number: &BigInt) -> Option<&bool> {
+        self.primes.get(number)
+    }
+
+    fn insert(&mut self, number: BigInt, prime: bool) {
+        if self.size == 0 || number.bits() > MAX_CACHED_BITS || self.primes.contains_key(&number) {
+            return;
+        }
+        if self.order.len() == self.size {
+            if let Some(oldest) = self.order.pop_front() {
+                self.primes.remove(&oldest);
+            }
+        }
+        self.order.push_back(number.clone());
+        self.primes.insert(number, prime);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cache, MAX_CACHED_BITS};
+    use crate::deadline::Deadline;
+    use num_bigint::BigInt;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sieve_and_beyond() {
+        let cache = Cache::new(100, 2);
+        let primes: Vec<i64> = (-5..=110)
+            .filter(|&n| {
+                cache
+                    .is_prime(&BigInt::from(n), &Deadline::never())
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(vec![97, 101, 103, 107, 109], primes[primes.len() - 5..]);
+        assert_eq!(2, primes[0]);
+        assert_eq!(25, cache.prime_count(100));
+        assert_eq!(29, cache.prime_count(110));
+    }
+
+    #[test]
+    fn test_bounded() {
+        let cache = Cache::new(10, 2);
+        for n in [11, 12, 13, 12] {
+            _ = cache.is_prime(&BigInt::from(n), &Deadline::never());
+        }
+        let results = cache.lock();
+        assert_eq!(2, results.primes.len());
+        assert_eq!(
+            vec![BigInt::from(12), BigInt::from(13)],
+            Vec::from(results.order.clone())
+        );
+        assert_eq!(Some(&true), results.get(&BigInt::from(13)));
+    }
+
+    #[test]
+    fn test_large_is_not_remembered() {
+        let cache = Cache::new(10, 2);
+        let large = (BigInt::from(1) << MAX_CACHED_BITS) + 1;
+        _ = cache.is_prime(&large, &Deadline::never());
+        assert!(cache.lock().primes.is_empty());
+        _ = cache.is_prime(&(large - 2), &Deadline::never());
+        assert_eq!(1, cache.lock().primes.len());
+    }
+
+    #[test]
+    fn test_expired_is_not_remembered() {
+        let cache = Cache::new(10, 2);
+        let large: BigInt = "18446744073709551629".parse().unwrap();
+        assert!(cache
+            .is_prime(&large, &Deadline::after(Some(Duration::ZERO)))
+            .is_err());
+        assert!(cache.lock().primes.is_empty());
+        assert_eq!(Ok(true), cache.is_prime(&large, &Deadline::never()));
+    }
+}
@@ -0,0 +1,47 @@
+//! A limit on how long one request may compute for, checked between the steps of calculations
+//! that can run long (each squaring in Miller-Rabin, candidate and Pollard's rho iteration).
+
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline(Option<Instant>);
+
+/// The deadline passed before the calculation finished.
+#[derive(Debug, PartialEq)]
+pub struct Expired;
+
+impl Deadline {
+    /// A deadline `limit` from now, or none at all.
+    pub fn after(limit: Option<Duration>) -> Self {
+        Self(limit.map(|limit| Instant::now() + limit))
+    }
+
+    /// No deadline at all, for tests of the calculations themselves.
+    #[cfg(test)]
+    pub const fn never() -> Self {
+        Self(None)
+    }
+
+    pub fn check(&self) -> Result<(), Expired> {
+        match self.0 {
+            Some(deadline) if Instant::now() >= deadline => Err(Expired),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Deadline, Expired};
+    use std::time::Duration;
+
+    #[test]
+    fn test_check() {
+        assert_eq!(Ok(()), Deadline::never().check());
+        assert_eq!(
+            Ok(()),
+            Deadline::after(Some(Duration::from_secs(60))).check()
+        );
+        assert_eq!(Err(Expired), Deadline::after(Some(Duration::ZERO)).check());
+    }
+}
@@ -0,0 +1,144 @@
+//! Binary encodings a connection can switch to, for clients that would rather not parse JSON.
+//! With `Options::binary`, a client whose first line is `{"encoding":"msgpack"}` (or `"cbor"`)
+//! gets that line back as confirmation. From then on each request and response is a MessagePack
+//! or CBOR map with the same fields as the JSON, in a frame prefixed by its length as a 4-byte
+//! big-endian integer. Neither encoding has integers beyond 64 bits, so larger numbers are strings
+//! of their decimal digits instead, in requests as in responses.
+
+use crate::number;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// Bytes in the length prefix of each frame.
+pub const PREFIX_LENGTH: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    MessagePack,
+    Cbor,
+}
+impl FromStr for Encoding {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "msgpack" => Ok(Self::MessagePack),
+            "cbor" => Ok(Self::Cbor),
+            _ => Err(format!("Unknown encoding \"{s}\" (msgpack, cbor).")),
+        }
+    }
+}
+impl Encoding {
+    /// The encoding that a connection's first line asks for, if that line is a negotiation.
+    pub(crate) fn negotiated(line: &[u8]) -> Option<Self> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Negotiation {
+            encoding: String,
+        }
+        let negotiation: Negotiation = serde_json::from_slice(line).ok()?;
+        negotiation.encoding.parse().ok()
+    }
+
+    /// One request from a frame's contents, as the JSON value it would have been (so with a
+    /// `number` written as a string of digits as that number).
+    pub(crate) fn decode(self, payload: &[u8]) -> Result<Value, String> {
+        let mut request: Value = match self {
+            Self::MessagePack => rmp_serde::from_slice(payload).map_err(|err| err.to_string())?,
+            Self::Cbor => ciborium::from_reader(payload).map_err(|err| err.to_string())?,
+        };
+        if let Some(number) = request.get_mut("number") {
+            if let Value::String(digits) = number {
+                if number::is_integer_literal(digits) {
+                    *number = serde_json::from_str(digits).map_err(|err| err.to_string())?;
+                }
+            }
+        }
+        Ok(request)
+    }
+
+    /// A whole frame, length prefix included. Structs are maps keyed by field name.
+    pub(crate) fn encode<T: Serialize>(self, value: &T) -> Vec<u8> {
+        let mut frame = vec![0; PREFIX_LENGTH];
+        match self {
+            Self::MessagePack => {
+                let mut serializer = rmp_serde::Serializer::new(&mut frame).with_struct_map();
+                value
+                    .serialize(&mut serializer)
+                    .expect("Responses are always valid MessagePack.");
+            }
+            Self::Cbor => {
+                ciborium::into_writer(value, &mut frame).expect("Responses are always valid CBOR.")
+            }
+        }
+        let length = (frame.len() - PREFIX_LENGTH) as u32;
+        frame[..PREFIX_LENGTH].copy_from_slice(&length.to_be_bytes());
+        frame
+    }
+}
+
+/// The length of the frame at the start of `queue`, once its prefix has arrived.
+pub(crate) fn frame_length(queue: &[u8]) -> Option<usize> {
+    let prefix: [u8; PREFIX_LENGTH] = queue.get(..PREFIX_LENGTH)?.try_into().ok()?;
+    Some(u32::from_be_bytes(prefix) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frame_length, Encoding, PREFIX_LENGTH};
+    use serde_json::json;
+
+    #[test]
+    fn test_negotiated() {
+        assert_eq!(
+            Some(Encoding::MessagePack),
+            Encoding::negotiated(b"{\"encoding\":\"msgpack\"}\n")
+        );
+        assert_eq!(
+            Some(Encoding::Cbor),
+            Encoding::negotiated(b" {\"encoding\": \"cbor\"}\n")
+        );
+        assert_eq!(None, Encoding::negotiated(b"{\"encoding\":\"xml\"}\n"));
+        assert_eq!(
+            None,
+            Encoding::negotiated(b"{\"encoding\":\"cbor\",\"method\":\"isPrime\"}\n")
+        );
+        assert_eq!(
+            None,
+            Encoding::negotiated(b"{\"method\":\"isPrime\",\"number\":7}\n")
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let request = json!({"method": "isPrime", "number": 7});
+        for encoding in [Encoding::MessagePack, Encoding::Cbor] {
+            let frame = encoding.encode(&request);
+            assert_eq!(Some(frame.len() - PREFIX_LENGTH), frame_length(&frame));
+            assert_eq!(
+                Ok(request.clone()),
+                encoding.decode(&frame[PREFIX_LENGTH..])
+            );
+        }
+    }
+
+    #[test]
+    fn test_digit_strings() {
+        let request = json!({"method": "isPrime", "number": "-18446744073709551629"});
+        let number: serde_json::Number = "-18446744073709551629".parse().unwrap();
+        for encoding in [Encoding::MessagePack, Encoding::Cbor] {
+            let frame = encoding.encode(&request);
+            let decoded = encoding.decode(&frame[PREFIX_LENGTH..]).unwrap();
+            assert_eq!(json!(number), decoded["number"]);
+            let frame = encoding.encode(&json!({"method": "isPrime", "number": "7.5"}));
+            let decoded = encoding.decode(&frame[PREFIX_LENGTH..]).unwrap();
+            assert_eq!(json!("7.5"), decoded["number"]);
+        }
+    }
+
+    #[test]
+    fn test_frame_length() {
+        assert_eq!(None, frame_length(&[0, 0, 1]));
+        assert_eq!(Some(258), frame_length(&[0, 0, 1, 2, 0xff]));
+    }
+}
@@ -0,0 +1,325 @@
+//! JSON-RPC 2.0 (<https://www.jsonrpc.org/specification>) over the same newline-delimited stream,
+//! so that general JSON-RPC clients can call every method. `params` are the fields the method's
+//! request would have (`{"number": 7}`), or the same values by position (`[7]`).
+
+use crate::cache::Cache;
+use crate::deadline::Deadline;
+use crate::profile::Profile;
+use crate::{call, CallError, Response};
+use common::ASCII_NEWLINE;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::time::Duration;
+
+const PARSE_ERROR: (i64, &str) = (-32_700, "Parse error");
+const INVALID_REQUEST: (i64, &str) = (-32_600, "Invalid Request");
+const METHOD_NOT_FOUND: (i64, &str) = (-32_601, "Method not found");
+const INVALID_PARAMS: (i64, &str) = (-32_602, "Invalid params");
+// From the range the specification reserves for implementation-defined server errors.
+const REQUEST_TOO_LONG: (i64, &str) = (-32_001, "Request too long");
+const DEADLINE_EXCEEDED: (i64, &str) = (-32_002, "Deadline exceeded");
+
+#[derive(Serialize, Debug)]
+struct RpcResponse {
+    jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Response>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+    id: Value,
+}
+impl RpcResponse {
+    fn result(id: Value, result: Response) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            result: Some(result),
+            error: None,
+            id,
+        }
+    }
+
+    fn error(id: Value, (code, message): (i64, &'static str)) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            result: None,
+            error: Some(RpcError { code, message }),
+            id,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct RpcError {
+    code: i64,
+    message: &'static str,
+}
+
+/// Answer one line (a request or a batch of them) within `limit`, which a batch shares between all
+/// its requests. Notifications get no response, so neither does a line made up only of
+/// notifications.
+pub(crate) fn process_line(
+    line: &[u8],
+    cache: &Cache,
+    limit: Option<Duration>,
+    profile: Profile,
+) -> Option<Vec<u8>> {
+    let deadline = &Deadline::after(limit);
+    let response = match serde_json::from_slice::<Value>(line) {
+        Err(_) => serde_json::to_vec(&RpcResponse::error(Value::Null, PARSE_ERROR)),
+        Ok(Value::Array(batch)) if batch.is_empty() => {
+            serde_json::to_vec(&RpcResponse::error(Value::Null, INVALID_REQUEST))
+        }
+        Ok(Value::Array(batch)) => {
+            let responses: Vec<RpcResponse> = batch
+                .into_iter()
+                .filter_map(|request| process(request, cache, deadline, profile))
+                .collect();
+            if responses.is_empty() {
+                return None;
+            }
+            serde_json::to_vec(&responses)
+        }
+        Ok(request) => serde_json::to_vec(&process(request, cache, deadline, profile)?),
+    };
+    let mut response = response.ok()?;
+    response.push(ASCII_NEWLINE);
+    Some(response)
+}
+
+/// The response to a line too long to read, whose id is unknown.
+pub(crate) fn too_long() -> Vec<u8> {
+    let mut response = serde_json::to_vec(&RpcResponse::error(Value::Null, REQUEST_TOO_LONG))
+        .expect("Responses are always valid JSON.");
+    response.push(ASCII_NEWLINE);
+    response
+}
+
+fn process(
+    request: Value,
+    cache: &Cache,
+    deadline: &Deadline,
+    profile: Profile,
+) -> Option<RpcResponse> {
+    let Value::Object(mut request) = request else {
+        return Some(RpcResponse::error(Value::Null, INVALID_REQUEST));
+    };
+    let id = request.remove("id");
+    let parsed = parse(&mut request, &id);
+    // Strictly, nothing but the version may be left alongside the method, params and id.
+    let extra = profile == Profile::Strict && request.len() > 1;
+    let (method, params) = match parsed {
+        Some(parsed) if !extra => parsed,
+        // An id that is not valid is not echoed back either.
+        _ => {
+            let id = id.filter(valid_id).unwrap_or_default();
+            return Some(RpcResponse::error(id, INVALID_REQUEST));
+        }
+    };
+    let outcome = match profile.check(&method, &params, &[]) {
+        Ok(()) => call(&method, params, cache, deadline),
+        Err(_) => Err(CallError::InvalidParams),
+    };
+    // Without an id, this is a notification: the client wants no response, even to an error.
+    let id = id?;
+    Some(match outcome {
+        Ok(response) => RpcResponse::result(id, response),
+        Err(CallError::UnknownMethod) => RpcResponse::error(id, METHOD_NOT_FOUND),
+        Err(CallError::InvalidParams) => RpcResponse::error(id, INVALID_PARAMS),
+        Err(CallError::DeadlineExceeded) => RpcResponse::error(id, DEADLINE_EXCEEDED),
+    })
+}
+
+/// Whether `id` may identify a request: ids are strings, numbers or null.
+fn valid_id(id: &Value) -> bool {
+    matches!(id, Value::Null | Value::String(_) | Value::Number(_))
+}
+
+/// The method and params of a valid request object.
+fn parse(request: &mut Map<String, Value>, id: &Option<Value>) -> Option<(String, Value)> {
+    let invalid_id = matches!(id, Some(id) if !valid_id(id));
+    if invalid_id || request.get("jsonrpc") != Some(&Value::from("2.0")) {
+        return None;
+    }
+    let Some(Value::String(method)) = request.remove("method") else {
+        return None;
+    };
+    match request.remove("params") {
+        None => Some((method, Value::Object(Map::new()))),
+        Some(params @ (Value::Object(_) | Value::Array(_))) => Some((method, params)),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{process_line, too_long};
+    use crate::cache::Cache;
+    use crate::profile::Profile;
+    use serde_json::{json, Value};
+    use std::time::{Duration, Instant};
+
+    fn respond_with(line: &str, limit: Option<Duration>, profile: Profile) -> Option<Value> {
+        process_line(line.as_bytes(), &Cache::new(100, 0), limit, profile)
+            .map(|response| serde_json::from_slice(&response).unwrap())
+    }
+
+    fn respond(line: &str) -> Option<Value> {
+        respond_with(line, None, Profile::Lenient)
+    }
+
+    #[test]
+    fn test_request() {
+        assert_eq!(
+            Some(
+                json!({"jsonrpc": "2.0", "result": {"method": "isPrime", "prime": true}, "id": 1})
+            ),
+            respond(r#"{"jsonrpc":"2.0","method":"isPrime","params":{"number":7},"id":1}"#)
+        );
+        assert_eq!(
+            Some(
+                json!({"jsonrpc": "2.0", "result": {"method": "isProbablePrime", "prime": false}, "id": "a"})
+            ),
+            respond(r#"{"jsonrpc":"2.0","method":"isProbablePrime","params":[8,5],"id":"a"}"#)
+        );
+    }
+
+    #[test]
+    fn test_notification() {
+        assert_eq!(
+            None,
+            respond(r#"{"jsonrpc":"2.0","method":"isPrime","params":[7]}"#)
+        );
+        assert_eq!(None, respond(r#"{"jsonrpc":"2.0","method":"nope"}"#));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |code: i64, id: Value| {
+            let message = match code {
+                -32_700 => "Parse error",
+                -32_600 => "Invalid Request",
+                -32_601 => "Method not found",
+                -32_001 => "Request too long",
+                -32_002 => "Deadline exceeded",
+                _ => "Invalid params",
+            };
+            Some(json!({"jsonrpc": "2.0", "error": {"code": code, "message": message}, "id": id}))
+        };
+        assert_eq!(error(-32_700, Value::Null), respond(r#"{"jsonrpc":"2.0","#));
+        assert_eq!(error(-32_600, Value::Null), respond("[]"));
+        assert_eq!(error(-32_600, Value::Null), respond("7"));
+        assert_eq!(
+            error(-32_600, json!(2)),
+            respond(r#"{"jsonrpc":"1.0","method":"isPrime","params":[7],"id":2}"#)
+        );
+        assert_eq!(
+            error(-32_600, json!(3)),
+            respond(r#"{"jsonrpc":"2.0","method":"isPrime","params":7,"id":3}"#)
+        );
+        assert_eq!(
+            error(-32_601, json!(4)),
+            respond(r#"{"jsonrpc":"2.0","method":"isPrim","params":[7],"id":4}"#)
+        );
+        assert_eq!(
+            error(-32_602, json!(5)),
+            respond(r#"{"jsonrpc":"2.0","method":"isPrime","params":{"number":"7"},"id":5}"#)
+        );
+        assert_eq!(
+            error(-32_602, Value::Null),
+            respond(r#"{"jsonrpc":"2.0","method":"factorize","params":[0],"id":null}"#)
+        );
+        assert_eq!(
+            error(-32_600, Value::Null),
+            respond(r#"{"jsonrpc":"2.0","method":"isPrime","params":[7],"id":{"a":1}}"#)
+        );
+        assert_eq!(
+            error(-32_002, json!(6)),
+            respond_with(
+                r#"{"jsonrpc":"2.0","method":"nextPrime","params":[18446744073709551616],"id":6}"#,
+                Some(Duration::ZERO),
+                Profile::Lenient
+            )
+        );
+        assert_eq!(
+            error(-32_001, Value::Null),
+            Some(serde_json::from_slice(&too_long()).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_batch() {
+        let batch = r#"[
+            {"jsonrpc":"2.0","method":"isPrime","params":[7],"id":1},
+            {"jsonrpc":"2.0","method":"isPrime","params":[8]},
+            1,
+            {"jsonrpc":"2.0","method":"nextPrime","params":[8],"id":2}
+        ]"#
+        .replace('\n', "");
+        assert_eq!(
+            Some(json!([
+                {"jsonrpc": "2.0", "result": {"method": "isPrime", "prime": true}, "id": 1},
+                {"jsonrpc": "2.0", "error": {"code": -32_600, "message": "Invalid Request"}, "id": null},
+                {"jsonrpc": "2.0", "result": {"method": "nextPrime", "number": 11}, "id": 2},
+            ])),
+            respond(&batch)
+        );
+        assert_eq!(
+            None,
+            respond(r#"[{"jsonrpc":"2.0","method":"isPrime","params":[8]}]"#)
+        );
+    }
+
+    #[test]
+    fn test_batch_deadline() {
+        // The product of two large primes, which takes far longer than the limit to factorize.
+        let request = r#"{"jsonrpc":"2.0","method":"factorize","params":[1427247692705959880439315947500961989719490561],"id":1}"#;
+        let batch = format!("[{}]", [request; 5].join(","));
+        let limit = Duration::from_millis(100);
+        let start = Instant::now();
+        let responses = respond_with(&batch, Some(limit), Profile::Lenient).unwrap();
+        // The requests share one deadline, rather than each getting the whole limit.
+        assert!(start.elapsed() < limit * 3);
+        for response in responses.as_array().unwrap() {
+            assert_eq!(json!(-32_002), response["error"]["code"]);
+        }
+    }
+
+    #[test]
+    fn test_strict() {
+        let strict = |line: &str| respond_with(line, None, Profile::Strict);
+        let code = |response: Option<Value>| response.unwrap()["error"]["code"].clone();
+        let prime =
+            json!({"jsonrpc": "2.0", "result": {"method": "isPrime", "prime": true}, "id": 1});
+        assert_eq!(
+            Some(prime.clone()),
+            strict(r#"{"jsonrpc":"2.0","method":"isPrime","params":{"number":7},"id":1}"#)
+        );
+        assert_eq!(
+            Some(prime),
+            strict(r#"{"jsonrpc":"2.0","method":"isPrime","params":[7],"id":1}"#)
+        );
+        assert_eq!(
+            json!(-32_600),
+            code(strict(
+                r#"{"jsonrpc":"2.0","method":"isPrime","params":[7],"id":1,"x":0}"#
+            ))
+        );
+        assert_eq!(
+            json!(-32_602),
+            code(strict(
+                r#"{"jsonrpc":"2.0","method":"isPrime","params":{"number":7,"x":0},"id":1}"#
+            ))
+        );
+        assert_eq!(
+            json!(-32_602),
+            code(strict(
+                r#"{"jsonrpc":"2.0","method":"isPrime","params":[7.0],"id":1}"#
+            ))
+        );
+        assert_eq!(
+            json!(true),
+            respond(r#"{"jsonrpc":"2.0","method":"isPrime","params":[7.0],"id":1,"x":0}"#).unwrap()
+                ["result"]["prime"]
+        );
+    }
+}
@@ -0,0 +1,561 @@
+extern crate serde_json;
+
+mod cache;
+mod deadline;
+mod encoding;
+mod jsonrpc;
+mod malformed;
+mod methods;
+mod number;
+mod pool;
+mod primality;
+mod profile;
+mod sieve;
+
+use cache::Cache;
+pub use cache::{DEFAULT_CACHE_SIZE, DEFAULT_SIEVE_LIMIT};
+use common::{serve, spawn_tcp_for_test, ServerHandle, ASCII_NEWLINE, BUFFER_SIZE};
+use deadline::{Deadline, Expired};
+pub use encoding::Encoding;
+pub use malformed::Malformed;
+use number::Number;
+use pool::Pool;
+pub use profile::Profile;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+const MALFORMED_RESPONSE: [u8; 5] = [69, 82, 82, 79, 82]; // "ERROR"
+
+// Used when the number of CPUs is unknown.
+const DEFAULT_WORKERS: usize = 4;
+// Requests read ahead of the responses being written, before the connection stops reading. Few,
+// so that one connection cannot fill the pool with work while every other connection waits.
+const MAX_PENDING: usize = 4;
+
+/// Longest request line (not counting its newline) read before it gets an error instead.
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 16_384;
+/// Longest one request may compute for before it gets an error instead.
+pub const DEFAULT_DEADLINE: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize, Debug)]
+struct PrimeRequest {
+    // Kept as text (`arbitrary_precision`), so large integers are not rounded through `f64`.
+    number: serde_json::Number,
+}
+
+#[derive(Serialize, Debug)]
+struct PrimeResponse {
+    method: String,
+    prime: bool,
+}
+
+/// Which protocol clients speak over the newline-delimited stream.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Protocol {
+    /// Exactly as the specification describes: a malformed request gets `ERROR` and a disconnect.
+    #[default]
+    Spec,
+    /// JSON-RPC 2.0, with ids, batches and error objects (and no disconnect on errors).
+    JsonRpc,
+}
+impl FromStr for Protocol {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spec" => Ok(Self::Spec),
+            "jsonrpc" => Ok(Self::JsonRpc),
+            _ => Err(format!("Unknown protocol \"{s}\" (spec, jsonrpc).")),
+        }
+    }
+}
+
+/// What a malformed request gets in response, before the disconnect (with the spec protocol).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ErrorStyle {
+    /// Just `ERROR`, as the specification allows.
+    #[default]
+    Spec,
+    /// A JSON object saying what was wrong, such as
+    /// `{"error":"unknownMethod","message":"unknown method \"isPrim\""}`.
+    Descriptive,
+}
+impl FromStr for ErrorStyle {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spec" => Ok(Self::Spec),
+            "descriptive" => Ok(Self::Descriptive),
+            _ => Err(format!("Unknown error style \"{s}\" (spec, descriptive).")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Options {
+    pub protocol: Protocol,
+    pub errors: ErrorStyle,
+    /// Numbers up to this are looked up in a sieve built when the server starts.
+    pub sieve_limit: u64,
+    /// How many primality results above the sieve limit to remember (for numbers of up to 512
+    /// bits, as larger ones never are).
+    pub cache_size: usize,
+    /// Threads answering requests, shared by every connection.
+    pub workers: usize,
+    /// A longer request line is answered with an error (and, with the spec protocol, a
+    /// disconnect) without reading the rest of it.
+    pub max_line_length: usize,
+    /// A request still computing after this long is answered with an error instead, or never with
+    /// `None`.
+    pub deadline: Option<Duration>,
+    /// Let clients switch their connection to MessagePack or CBOR (see `Encoding`), with the spec
+    /// protocol only: JSON-RPC connections never switch.
+    pub binary: bool,
+    /// Whether requests may have fields their method ignores, and integers like `7.0`.
+    pub profile: Profile,
+}
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            protocol: Protocol::default(),
+            errors: ErrorStyle::default(),
+            sieve_limit: DEFAULT_SIEVE_LIMIT,
+            cache_size: DEFAULT_CACHE_SIZE,
+            workers: thread::available_parallelism().map_or(DEFAULT_WORKERS, usize::from),
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+            deadline: Some(DEFAULT_DEADLINE),
+            binary: false,
+            profile: Profile::default(),
+        }
+    }
+}
+impl Options {
+    /// Every connection handled by the returned handler shares one cache and worker pool.
+    pub fn handler(self) -> impl Fn(TcpStream) + Clone + Send + Sync + 'static {
+        let server = Arc::new(Server::new(self));
+        move |stream| server.handle(stream)
+    }
+}
+
+pub fn handle_stream(stream: TcpStream) {
+    default_server().handle(stream);
+}
+
+/// The server behind `handle_stream`, with the default options.
+fn default_server() -> &'static Arc<Server> {
+    static SERVER: OnceLock<Arc<Server>> = OnceLock::new();
+    SERVER.get_or_init(|| Arc::new(Server::new(Options::default())))
+}
+
+/// What to do about one request line, once it has been answered.
+enum Reply {
+    Respond(Vec<u8>),
+    /// Nothing to send (for JSON-RPC notifications).
+    Nothing,
+    /// Send this, then close the connection.
+    Disconnect(Vec<u8>),
+}
+
+struct Server {
+    options: Options,
+    cache: Cache,
+    pool: Pool,
+}
+impl Server {
+    fn new(options: Options) -> Self {
+        Self {
+            options,
+            cache: Cache::new(options.sieve_limit, options.cache_size),
+            pool: Pool::new(options.workers),
+        }
+    }
+
+    /// Read request lines and hand each to the pool (unless a lookup answers it), while a second
+    /// thread writes the replies in the order the requests arrived. A line longer than the limit is answered straight away, and
+    /// the rest of it is discarded as it arrives. Once the first line negotiates a binary encoding,
+    /// requests are length-prefixed frames instead of lines.
+    fn handle(self: &Arc<Self>, mut stream: TcpStream) {
+        let writer = match stream.try_clone() {
+            Ok(writer) => writer,
+            Err(_) => return,
+        };
+        let (replies, pending) = mpsc::sync_channel::<Receiver<Reply>>(MAX_PENDING);
+        let writing = thread::spawn(move || write_replies(writer, pending));
+        let mut queue: Vec<u8> = vec![];
+        let mut buffer: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
+        let mut discarding = false;
+        let mut first_line = true;
+        let mut encoding: Option<Encoding> = None;
+
+        'connected: loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => break 'connected,
+                Ok(n) => queue.extend_from_slice(&buffer[0..n]),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => (),
+                Err(_) => break 'connected,
+            };
+
+            loop {
+                if let Some(encoding) = encoding {
+                    let Some(length) = encoding::frame_length(&queue) else {
+                        break;
+                    };
+                    if length > self.options.max_line_length {
+                        let too_long = Malformed::TooLong(self.options.max_line_length);
+                        _ = replies.send(ready(self.disconnect_encoded(encoding, too_long)));
+                        break 'connected;
+                    }
+                    if queue.len() < encoding::PREFIX_LENGTH + length {
+                        break;
+                    }
+                    let frame: Vec<u8> = queue
+                        .drain(..encoding::PREFIX_LENGTH + length)
+                        .skip(encoding::PREFIX_LENGTH)
+                        .collect();
+
+                    let lookup = encoding
+                        .decode(&frame)
+                        .map_or(true, |request| self.is_lookup(&request));
+                    let reply = match lookup {
+                        true => ready(self.process_frame(encoding, &frame)),
+                        false => {
+                            let server = Arc::clone(self);
+                            self.pool
+                                .submit(move || server.process_frame(encoding, &frame))
+                        }
+                    };
+                    if replies.send(reply).is_err() {
+                        break 'connected;
+                    }
+                    continue;
+                }
+
+                let newline = queue.iter().position(|&byte| byte == ASCII_NEWLINE);
+                if discarding {
+                    match newline {
+                        Some(position) => {
+                            queue.drain(..position + 1);
+                            discarding = false;
+                            continue;
+                        }
+                        None => {
+                            queue.clear();
+                            break;
+                        }
+                    }
+                }
+                if newline.unwrap_or(queue.len()) > self.options.max_line_length {
+                    let reply = self.too_long();
+                    let disconnect = matches!(reply, Reply::Disconnect(_));
+                    if replies.send(ready(reply)).is_err() || disconnect {
+                        break 'connected;
+                    }
+                    discarding = true;
+                    continue;
+                }
+                let Some(position) = newline else {
+                    break;
+                };
+
+                let mut line: Vec<u8> = vec![];
+                line.extend_from_slice(queue.drain(..position + 1).as_slice());
+                if std::mem::take(&mut first_line) && self.binary() {
+                    encoding = Encoding::negotiated(&line);
+                    if encoding.is_some() {
+                        // Confirmed by echoing the negotiation back.
+                        if replies.send(ready(Reply::Respond(line))).is_err() {
+                            break 'connected;
+                        }
+                        continue;
+                    }
+                }
+
+                let lookup =
+                    serde_json::from_slice(&line).map_or(true, |request| self.is_lookup(&request));
+                let reply = match lookup {
+                    true => ready(self.process_line(&line)),
+                    false => {
+                        let server = Arc::clone(self);
+                        self.pool.submit(move || server.process_line(&line))
+                    }
+                };
+                // The writer only stops early once it has closed the connection.
+                if replies.send(reply).is_err() {
+                    break 'connected;
+                }
+            }
+        }
+
+        drop(replies);
+        _ = writing.join();
+    }
+
+    /// Whether answering `request` takes no more than a lookup, so is quicker done straight away
+    /// than behind other connections' requests in the pool. Only `isPrime` of a number the cache
+    /// knows (or of no integer at all) is, and anything else might compute for a while.
+    fn is_lookup(&self, request: &Value) -> bool {
+        let params = match self.options.protocol {
+            Protocol::Spec => Some(request),
+            Protocol::JsonRpc => request.get("params"),
+        };
+        if request.get("method") != Some(&Value::from("isPrime")) {
+            return false;
+        }
+        let number = params.and_then(|params| params.get("number").or_else(|| params.get(0)));
+        let Some(Value::Number(number)) = number else {
+            return true;
+        };
+        match Number::parse(&number.to_string()) {
+            Ok(Number::Integer(integer)) => self.cache.knows(&integer),
+            _ => true,
+        }
+    }
+
+    fn binary(&self) -> bool {
+        self.options.binary && self.options.protocol == Protocol::Spec
+    }
+
+    fn process_line(&self, line: &[u8]) -> Reply {
+        let Options {
+            deadline, profile, ..
+        } = self.options;
+        match self.options.protocol {
+            Protocol::Spec => match process_json(line, &self.cache, deadline, profile) {
+                Ok(response) => Reply::Respond(response),
+                Err(malformed) => self.disconnect(malformed),
+            },
+            Protocol::JsonRpc => {
+                match jsonrpc::process_line(line, &self.cache, deadline, profile) {
+                    Some(response) => Reply::Respond(response),
+                    None => Reply::Nothing,
+                }
+            }
+        }
+    }
+
+    fn process_frame(&self, encoding: Encoding, frame: &[u8]) -> Reply {
+        let Options {
+            deadline, profile, ..
+        } = self.options;
+        let response = encoding
+            .decode(frame)
+            .map_err(Malformed::Undecodable)
+            .and_then(|request| process_request(&request, &self.cache, deadline, profile));
+        match response {
+            Ok(response) => Reply::Respond(encoding.encode(&response)),
+            Err(malformed) => self.disconnect_encoded(encoding, malformed),
+        }
+    }
+
+    fn too_long(&self) -> Reply {
+        match self.options.protocol {
+            Protocol::Spec => self.disconnect(Malformed::TooLong(self.options.max_line_length)),
+            Protocol::JsonRpc => Reply::Respond(jsonrpc::too_long()),
+        }
+    }
+
+    fn disconnect(&self, malformed: Malformed) -> Reply {
+        Reply::Disconnect(match self.options.errors {
+            ErrorStyle::Spec => MALFORMED_RESPONSE.to_vec(),
+            ErrorStyle::Descriptive => malformed.response(),
+        })
+    }
+
+    /// The same as `disconnect`, in a frame.
+    fn disconnect_encoded(&self, encoding: Encoding, malformed: Malformed) -> Reply {
+        Reply::Disconnect(match self.options.errors {
+            ErrorStyle::Spec => encoding.encode(&"ERROR"),
+            ErrorStyle::Descriptive => encoding.encode(&malformed.body()),
+        })
+    }
+}
+
+/// A reply that needs no work from the pool, to send in order with the others.
+fn ready(reply: Reply) -> Receiver<Reply> {
+    let (sender, receiver) = mpsc::channel();
+    _ = sender.send(reply);
+    receiver
+}
+
+/// Write each reply as soon as it and every reply before it are ready, then close the connection
+/// once the reader has finished (or a reply asks to disconnect).
+fn write_replies(mut stream: TcpStream, pending: Receiver<Receiver<Reply>>) {
+    for reply in pending {
+        match reply.recv() {
+            Ok(Reply::Respond(response)) => {
+                if stream.write_all(&response).is_err() {
+                    break;
+                }
+            }
+            Ok(Reply::Nothing) => (),
+            Ok(Reply::Disconnect(response)) => {
+                _ = stream.write_all(&response);
+                break;
+            }
+            // The request could not be answered.
+            Err(_) => break,
+        }
+    }
+
+    _ = stream.shutdown(Shutdown::Both);
+}
+
+fn process_json(
+    json: &[u8],
+    cache: &Cache,
+    limit: Option<Duration>,
+    profile: Profile,
+) -> Result<Vec<u8>, Malformed> {
+    let request: Value =
+        serde_json::from_slice(json).map_err(|err| Malformed::InvalidJson(err.to_string()))?;
+    let response = process_request(&request, cache, limit, profile)?;
+    let response = serde_json::to_string(&response).expect("Responses are always valid JSON.");
+    // Responses are newline-terminated, just like requests.
+    let mut response = response.into_bytes();
+    response.push(ASCII_NEWLINE);
+    Ok(response)
+}
+
+/// Answer one request, however it was encoded.
+fn process_request(
+    request: &Value,
+    cache: &Cache,
+    limit: Option<Duration>,
+    profile: Profile,
+) -> Result<Response, Malformed> {
+    let Value::Object(fields) = request else {
+        return Err(Malformed::NotAnObject);
+    };
+    let method = match fields.get("method") {
+        Some(Value::String(method)) => method,
+        Some(_) => return Err(Malformed::MethodNotAString),
+        None => return Err(Malformed::MissingMethod),
+    };
+    profile.check(method, request, &["method"])?;
+    call(method, request, cache, &Deadline::after(limit)).map_err(|err| match err {
+        CallError::UnknownMethod => Malformed::UnknownMethod(method.clone()),
+        CallError::InvalidParams => Malformed::invalid_params(method, fields),
+        CallError::DeadlineExceeded => Malformed::DeadlineExceeded,
+    })
+}
+
+/// Why a method gave no response.
+#[derive(Debug, PartialEq)]
+enum CallError {
+    UnknownMethod,
+    /// The request is missing fields the method needs, or their values are not ones it accepts.
+    InvalidParams,
+    /// Answering took longer than the request was allowed.
+    DeadlineExceeded,
+}
+impl From<Expired> for CallError {
+    fn from(_: Expired) -> Self {
+        Self::DeadlineExceeded
+    }
+}
+
+/// Any method's response, serialized exactly as that method's own response.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+enum Response {
+    IsPrime(PrimeResponse),
+    NextPrime(methods::NextPrimeResponse),
+    PreviousPrime(methods::PreviousPrimeResponse),
+    Factorize(methods::FactorizeResponse),
+    IsProbablePrime(methods::ProbablePrimeResponse),
+    PrimeCount(methods::PrimeCountResponse),
+}
+
+/// Parse `params` as the method's request, and answer it before `deadline`.
+fn call<'de, D>(
+    method: &str,
+    params: D,
+    cache: &Cache,
+    deadline: &Deadline,
+) -> Result<Response, CallError>
+where
+    D: Deserializer<'de>,
+{
+    fn parse<'de, Q: Deserialize<'de>, D: Deserializer<'de>>(params: D) -> Result<Q, CallError> {
+        Q::deserialize(params).map_err(|_| CallError::InvalidParams)
+    }
+    match method {
+        "isPrime" => is_prime(parse(params)?, cache, deadline).map(Response::IsPrime),
+        "nextPrime" => methods::next_prime(parse(params)?, deadline).map(Response::NextPrime),
+        "previousPrime" => {
+            methods::previous_prime(parse(params)?, deadline).map(Response::PreviousPrime)
+        }
+        "factorize" => methods::factorize(parse(params)?, deadline).map(Response::Factorize),
+        "isProbablePrime" => {
+            methods::is_probable_prime(parse(params)?, deadline).map(Response::IsProbablePrime)
+        }
+        "primeCount" => methods::prime_count(parse(params)?, cache).map(Response::PrimeCount),
+        _ => Err(CallError::UnknownMethod),
+    }
+}
+
+fn is_prime(
+    request: PrimeRequest,
+    cache: &Cache,
+    deadline: &Deadline,
+) -> Result<PrimeResponse, CallError> {
+    let number =
+        Number::parse(&request.number.to_string()).map_err(|()| CallError::InvalidParams)?;
+    Ok(PrimeResponse {
+        method: "isPrime".to_string(),
+        // Primes are whole numbers above one, so fractions and negative numbers never are, and
+        // huge numbers (written with an exponent) are multiples of ten.
+        prime: match number {
+            Number::Integer(integer) => cache.is_prime(&integer, deadline)?,
+            Number::Huge { .. } | Number::Fraction => false,
+        },
+    })
+}
+
+pub fn spawn_for_test() -> ServerHandle {
+    spawn_for_test_with_options(Options::default())
+}
+
+pub fn spawn_for_test_with_options(options: Options) -> ServerHandle {
+    spawn_tcp_for_test(move |listener, shutdown| {
+        serve(listener, options.handler(), false, &shutdown)
+    })
+}
+
+/// Request processing, for `benches/primes.rs`.
+#[cfg(feature = "bench")]
+pub mod bench {
+    pub fn process_json(json: &[u8]) -> Result<Vec<u8>, super::Malformed> {
+        let server = super::default_server();
+        super::process_json(
+            json,
+            &server.cache,
+            server.options.deadline,
+            server.options.profile,
+        )
+    }
+}
+
+/// Request lines, for the `primes_json` fuzz target.
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing {
+    /// Process one request line; every response (including descriptive errors) must be a
+    /// newline-terminated JSON object.
+    pub fn process_json(data: &[u8]) {
+        let server = super::default_server();
+        let options = &server.options;
+        let response =
+            match super::process_json(data, &server.cache, options.deadline, options.profile) {
+                Ok(response) => response,
+                Err(malformed) => malformed.response(),
+            };
+        assert_eq!(Some(&common::ASCII_NEWLINE), response.last());
+        assert!(serde_json::from_slice::<serde_json::Value>(&response).is_ok());
+    }
+}
@@ -0,0 +1,174 @@
+//! What was wrong with a malformed request, for the descriptive error responses.
+
+use crate::number::Number;
+use serde_json::{json, Map, Value};
+use std::fmt::Display;
+
+#[derive(Debug, PartialEq)]
+pub enum Malformed {
+    InvalidJson(String),
+    /// A frame that is not valid in the connection's binary encoding.
+    Undecodable(String),
+    NotAnObject,
+    MissingMethod,
+    MethodNotAString,
+    UnknownMethod(String),
+    MissingNumber,
+    /// The number is some other type of JSON value, such as a string.
+    NumberNotANumber(&'static str),
+    /// Methods other than `isPrime` only answer for integers.
+    NotAnInteger(String),
+    InvalidParams(String),
+    /// A field the method does not take (with the strict profile).
+    UnknownField(String),
+    /// A number written with a fraction or exponent (with the strict profile).
+    NotAnIntegerLiteral(String),
+    /// The request line was longer than this many bytes.
+    TooLong(usize),
+    /// Answering took longer than the request was allowed.
+    DeadlineExceeded,
+}
+impl Malformed {
+    /// Why `method` could not use the rest of the request's `fields`.
+    pub(crate) fn invalid_params(method: &str, fields: &Map<String, Value>) -> Self {
+        match fields.get("number") {
+            None => Self::MissingNumber,
+            Some(Value::Number(number)) => match Number::parse(&number.to_string()) {
+                Ok(Number::Integer(_)) => Self::InvalidParams(method.to_string()),
+                _ if method != "isPrime" => Self::NotAnInteger(method.to_string()),
+                _ => Self::InvalidParams(method.to_string()),
+            },
+            Some(other) => Self::NumberNotANumber(type_name(other)),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::InvalidJson(_) => "invalidJson",
+            Self::Undecodable(_) => "invalidEncoding",
+            Self::NotAnObject => "invalidRequest",
+            Self::MissingMethod | Self::MethodNotAString => "invalidMethod",
+            Self::UnknownMethod(_) => "unknownMethod",
+            Self::MissingNumber
+            | Self::NumberNotANumber(_)
+            | Self::NotAnInteger(_)
+            | Self::NotAnIntegerLiteral(_) => "invalidNumber",
+            Self::UnknownField(_) => "unknownField",
+            Self::InvalidParams(_) => "invalidParams",
+            Self::TooLong(_) => "requestTooLong",
+            Self::DeadlineExceeded => "deadlineExceeded",
+        }
+    }
+
+    /// The kind of error and a description of it.
+    pub(crate) fn body(&self) -> Value {
+        json!({"error": self.kind(), "message": self.to_string()})
+    }
+
+    /// A newline-terminated JSON object with the kind of error and a description of it.
+    pub fn response(&self) -> Vec<u8> {
+        let mut response = self.body().to_string().into_bytes();
+        response.push(common::ASCII_NEWLINE);
+        response
+    }
+}
+impl Display for Malformed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidJson(details) => write!(f, "invalid JSON: {details}"),
+            Self::Undecodable(details) => write!(f, "invalid frame: {details}"),
+            Self::NotAnObject => write!(f, "request must be a JSON object"),
+            Self::MissingMethod => write!(f, "missing method"),
+            Self::MethodNotAString => write!(f, "method must be a string"),
+            Self::UnknownMethod(method) => write!(f, "unknown method {method:?}"),
+            Self::MissingNumber => write!(f, "missing number"),
+            Self::NumberNotANumber(other) => write!(f, "number must be a number, not {other}"),
+            Self::NotAnInteger(method) => write!(f, "{method} needs an integer number"),
+            Self::InvalidParams(method) => write!(f, "invalid parameters for {method}"),
+            Self::UnknownField(field) => write!(f, "unknown field {field:?}"),
+            Self::NotAnIntegerLiteral(number) => {
+                write!(f, "number must be written as an integer, not {number}")
+            }
+            Self::TooLong(limit) => write!(f, "request longer than {limit} bytes"),
+            Self::DeadlineExceeded => write!(f, "deadline exceeded before answering"),
+        }
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Malformed;
+    use crate::cache::Cache;
+    use crate::process_json;
+    use crate::profile::Profile;
+    use std::time::Duration;
+
+    fn malformed(line: &str) -> Malformed {
+        process_json(line.as_bytes(), &Cache::new(100, 0), None, Profile::Lenient).expect_err(line)
+    }
+
+    #[test]
+    fn test_reasons() {
+        assert!(matches!(
+            malformed("{\"method\":\"isPrime\",\"number\":7"),
+            Malformed::InvalidJson(_)
+        ));
+        assert_eq!(Malformed::NotAnObject, malformed("[\"isPrime\",7]"));
+        assert_eq!(Malformed::MissingMethod, malformed("{\"number\":7}"));
+        assert_eq!(
+            Malformed::MethodNotAString,
+            malformed("{\"method\":7,\"number\":7}")
+        );
+        assert_eq!(
+            Malformed::UnknownMethod("isPrim".to_string()),
+            malformed("{\"method\":\"isPrim\",\"number\":7}")
+        );
+        assert_eq!(
+            Malformed::MissingNumber,
+            malformed("{\"method\":\"isPrime\"}")
+        );
+        assert_eq!(
+            Malformed::NumberNotANumber("a string"),
+            malformed("{\"method\":\"isPrime\",\"number\":\"7\"}")
+        );
+        assert_eq!(
+            Malformed::NotAnInteger("factorize".to_string()),
+            malformed("{\"method\":\"factorize\",\"number\":7.5}")
+        );
+        assert_eq!(
+            Malformed::InvalidParams("isProbablePrime".to_string()),
+            malformed("{\"method\":\"isProbablePrime\",\"number\":7}")
+        );
+        let line = "{\"method\":\"isPrime\",\"number\":18446744073709551629}";
+        assert_eq!(
+            Malformed::DeadlineExceeded,
+            process_json(
+                line.as_bytes(),
+                &Cache::new(100, 0),
+                Some(Duration::ZERO),
+                Profile::Lenient
+            )
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_response() {
+        assert_eq!(
+            b"{\"error\":\"unknownMethod\",\"message\":\"unknown method \\\"isPrim\\\"\"}\n"
+                .to_vec(),
+            Malformed::UnknownMethod("isPrim".to_string()).response()
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Methods beyond the `isPrime` the specification asks for. Each takes an integer `number` (a
+//! request with a fraction, or an integer too large to write out, is malformed) and answers with
+//! its own response fields alongside `method`.
+
+use crate::cache::Cache;
+use crate::deadline::Deadline;
+use crate::number::{Integer, Number};
+use crate::primality;
+use crate::CallError;
+use num_bigint::{BigInt, Sign};
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+/// The most Miller-Rabin rounds `isProbablePrime` will run.
+pub const MAX_ROUNDS: u32 = 256;
+/// The largest `number` that `primeCount` will sieve up to (beyond the cached sieve).
+pub const MAX_PRIME_COUNT: u64 = 10_000_000;
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct NextPrimeRequest {
+    number: serde_json::Number,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct NextPrimeResponse {
+    method: String,
+    number: Integer,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct PreviousPrimeRequest {
+    number: serde_json::Number,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct PreviousPrimeResponse {
+    method: String,
+    /// `null` when there is no smaller prime.
+    number: Option<Integer>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct FactorizeRequest {
+    number: serde_json::Number,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct FactorizeResponse {
+    method: String,
+    /// Ascending, and repeated according to multiplicity.
+    factors: Vec<Integer>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ProbablePrimeRequest {
+    number: serde_json::Number,
+    rounds: u32,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ProbablePrimeResponse {
+    method: String,
+    prime: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct PrimeCountRequest {
+    number: serde_json::Number,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct PrimeCountResponse {
+    method: String,
+    count: u64,
+}
+
+fn integer(number: &serde_json::Number) -> Result<BigInt, CallError> {
+    match Number::parse(&number.to_string()) {
+        Ok(Number::Integer(integer)) => Ok(integer),
+        _ => Err(CallError::InvalidParams),
+    }
+}
+
+pub(crate) fn next_prime(
+    request: NextPrimeRequest,
+    deadline: &Deadline,
+) -> Result<NextPrimeResponse, CallError> {
+    Ok(NextPrimeResponse {
+        method: "nextPrime".to_string(),
+        number: Integer(primality::next_prime(&integer(&request.number)?, deadline)?),
+    })
+}
+
+pub(crate) fn previous_prime(
+    request: PreviousPrimeRequest,
+    deadline: &Deadline,
+) -> Result<PreviousPrimeResponse, CallError> {
+    Ok(PreviousPrimeResponse {
+        method: "previousPrime".to_string(),
+        number: primality::previous_prime(&integer(&request.number)?, deadline)?.map(Integer),
+    })
+}
+
+/// Only positive integers have a factorization.
+pub(crate) fn factorize(
+    request: FactorizeRequest,
+    deadline: &Deadline,
+) -> Result<FactorizeResponse, CallError> {
+    let number = integer(&request.number)?;
+    if number.sign() != Sign::Plus {
+        return Err(CallError::InvalidParams);
+    }
+    Ok(FactorizeResponse {
+        method: "factorize".to_string(),
+        factors: primality::factorize(number.magnitude(), deadline)?
+            .into_iter()
+            .map(Integer::from)
+            .collect(),
+    })
+}
+
+pub(crate) fn is_probable_prime(
+    request: ProbablePrimeRequest,
+    deadline: &Deadline,
+) -> Result<ProbablePrimeResponse, CallError> {
+    if !(1..=MAX_ROUNDS).contains(&request.rounds) {
+        return Err(CallError::InvalidParams);
+    }
+    let number = integer(&request.number)?;
+    Ok(ProbablePrimeResponse {
+        method: "isProbablePrime".to_string(),
+        prime: primality::is_probable_prime(&number, request.rounds, deadline)?,
+    })
+}
+
+/// How many primes there are up to and including `number` (zero for anything below two). Sieving
+/// is bounded by `MAX_PRIME_COUNT` rather than the deadline.
+pub(crate) fn prime_count(
+    request: PrimeCountRequest,
+    cache: &Cache,
+) -> Result<PrimeCountResponse, CallError> {
+    let number = integer(&request.number)?;
+    let count = match number.sign() {
+        Sign::Minus => 0,
+        _ => match number.to_u64() {
+            Some(number) if number <= MAX_PRIME_COUNT => cache.prime_count(number),
+            _ => return Err(CallError::InvalidParams),
+        },
+    };
+    Ok(PrimeCountResponse {
+        method: "primeCount".to_string(),
+        count,
+    })
+}
@@ -0,0 +1,205 @@
+//! Request numbers, read from the digits of the JSON text rather than through `f64`, so that
+//! integers of any size keep their exact value.
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::ToPrimitive;
+use serde::{Serialize, Serializer};
+use std::str::FromStr;
+
+/// Integers are not written out when an exponent would make them longer than this many digits.
+const MAX_DIGITS: usize = 10_000;
+
+#[derive(Debug, PartialEq)]
+pub enum Number {
+    Integer(BigInt),
+    /// An integer too long to write out, such as `1e1000000`. These all end in zeros (so are
+    /// multiples of ten).
+    Huge {
+        negative: bool,
+    },
+    /// Anything with a non-zero fractional part, such as `7.5` or `1e-3`.
+    Fraction,
+}
+impl Number {
+    /// Parse the text of a JSON number: `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`.
+    pub fn parse(text: &str) -> Result<Self, ()> {
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, text),
+        };
+        let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, parse_exponent(exponent)?),
+            None => (unsigned, 0),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if !is_digits(whole) || (mantissa.contains('.') && !is_digits(fraction)) {
+            return Err(());
+        }
+
+        // The value is `digits` * 10^`shift`.
+        let digits = format!("{whole}{fraction}");
+        let digits = digits.trim_start_matches('0');
+        let shift = exponent.saturating_sub(fraction.len() as i64);
+        if digits.is_empty() {
+            return Ok(Self::Integer(BigInt::default()));
+        }
+        let integer = match shift {
+            // Only implied zeros make a number huge; digits written out in full are kept.
+            1.. if shift.saturating_add(digits.len() as i64) > MAX_DIGITS as i64 => {
+                return Ok(Self::Huge { negative })
+            }
+            0.. => format!("{digits}{}", "0".repeat(shift as usize)),
+            _ => {
+                let fractional = shift.unsigned_abs();
+                if fractional >= digits.len() as u64 {
+                    return Ok(Self::Fraction);
+                }
+                let (integer, fraction) = digits.split_at(digits.len() - fractional as usize);
+                if fraction.bytes().any(|digit| digit != b'0') {
+                    return Ok(Self::Fraction);
+                }
+                integer.to_string()
+            }
+        };
+        let magnitude = BigUint::parse_bytes(integer.as_bytes(), 10).ok_or(())?;
+        let sign = if negative { Sign::Minus } else { Sign::Plus };
+        Ok(Self::Integer(BigInt::from_biguint(sign, magnitude)))
+    }
+}
+
+/// An integer in a response, written out in full.
+#[derive(Debug, PartialEq)]
+pub struct Integer(pub BigInt);
+/// In JSON, written out in full however large. Binary encodings have no such numbers, so there
+/// anything beyond 64 bits is a string of its decimal digits.
+impl Serialize for Integer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return serde_json::Number::from_str(&self.0.to_string())
+                .map_err(serde::ser::Error::custom)?
+                .serialize(serializer);
+        }
+        match (self.0.to_i64(), self.0.to_u64()) {
+            (Some(small), _) => serializer.serialize_i64(small),
+            (_, Some(large)) => serializer.serialize_u64(large),
+            _ => serializer.serialize_str(&self.0.to_string()),
+        }
+    }
+}
+impl From<BigUint> for Integer {
+    fn from(natural: BigUint) -> Self {
+        Self(BigInt::from(natural))
+    }
+}
+
+/// Whether `text` is an integer written without a fraction or exponent, such as `-7`.
+pub(crate) fn is_integer_literal(text: &str) -> bool {
+    is_digits(text.strip_prefix('-').unwrap_or(text))
+}
+
+fn is_digits(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Exponents too large for an `i64` are clamped, which is still far past `MAX_DIGITS`.
+fn parse_exponent(text: &str) -> Result<i64, ()> {
+    let (negative, digits) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    if !is_digits(digits) {
+        return Err(());
+    }
+    let exponent = digits.bytes().fold(0i64, |exponent, digit| {
+        exponent
+            .saturating_mul(10)
+            .saturating_add((digit - b'0') as i64)
+    });
+    Ok(if negative { -exponent } else { exponent })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Integer, Number, MAX_DIGITS};
+    use num_bigint::BigInt;
+
+    fn integer(text: &str) -> Number {
+        Number::Integer(text.parse::<BigInt>().unwrap())
+    }
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(Ok(integer("7")), Number::parse("7"));
+        assert_eq!(Ok(integer("-7")), Number::parse("-7"));
+        assert_eq!(Ok(integer("0")), Number::parse("-0"));
+        assert_eq!(
+            Ok(integer("18446744073709551629")),
+            Number::parse("18446744073709551629")
+        );
+        assert_eq!(
+            Ok(integer("9007199254740993")),
+            Number::parse("9007199254740993")
+        );
+    }
+
+    #[test]
+    fn test_exponents_and_fractions() {
+        assert_eq!(Ok(integer("7")), Number::parse("7.0"));
+        assert_eq!(Ok(integer("7")), Number::parse("0.7e1"));
+        assert_eq!(Ok(integer("1200")), Number::parse("1.2E+3"));
+        assert_eq!(Ok(integer("0")), Number::parse("0.0e-5"));
+        assert_eq!(Ok(Number::Fraction), Number::parse("7.5"));
+        assert_eq!(Ok(Number::Fraction), Number::parse("1e-3"));
+        assert_eq!(Ok(Number::Fraction), Number::parse("-2.000001"));
+        assert_eq!(
+            Ok(Number::Fraction),
+            Number::parse("1e-99999999999999999999")
+        );
+    }
+
+    #[test]
+    fn test_huge() {
+        assert_eq!(
+            Ok(Number::Huge { negative: false }),
+            Number::parse("1e1000000")
+        );
+        assert_eq!(
+            Ok(Number::Huge { negative: true }),
+            Number::parse("-3e99999999999999999999")
+        );
+        let long = format!("1{}1", "0".repeat(MAX_DIGITS));
+        assert_eq!(
+            Ok(Number::Integer(long.parse().unwrap())),
+            Number::parse(&long)
+        );
+        assert_eq!(
+            Ok(Number::Integer(long.parse().unwrap())),
+            Number::parse(&format!("{long}.000"))
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        for text in ["", "-", "abc", "1.", ".5", "1e", "1e+", "0x10", "\"7\""] {
+            assert_eq!(Err(()), Number::parse(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn test_serialize_integer() {
+        let large = Integer("-170141183460469231731687303715884105727".parse().unwrap());
+        assert_eq!(
+            "-170141183460469231731687303715884105727",
+            serde_json::to_string(&large).unwrap()
+        );
+        assert_eq!(
+            vec![0x07],
+            rmp_serde::to_vec(&Integer(BigInt::from(7))).unwrap()
+        );
+        assert_eq!(
+            rmp_serde::to_vec("-170141183460469231731687303715884105727").unwrap(),
+            rmp_serde::to_vec(&large).unwrap()
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Worker threads shared by every connection. Each connection hands the requests that need
+//! computing to the pool, so a slow request only ties up one worker while the requests pipelined
+//! behind it are answered in parallel (and the connection keeps their responses in order).
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+pub struct Pool {
+    jobs: Sender<Job>,
+}
+impl Pool {
+    /// Start `workers` threads (at least one), which stop once the pool is dropped.
+    pub fn new(workers: usize) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers.max(1) {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || loop {
+                let job = match receiver.lock().expect("Primes pool poisoned.").recv() {
+                    Ok(job) => job,
+                    Err(_) => break,
+                };
+                // A panicking job (a bug) must not take the worker with it.
+                _ = panic::catch_unwind(AssertUnwindSafe(job));
+            });
+        }
+        Self { jobs }
+    }
+
+    /// Run `task` on the next free worker. Its result arrives on the returned receiver (which
+    /// disconnects without one if the task panics).
+    pub fn submit<T, F>(&self, task: F) -> Receiver<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (result, receiver) = mpsc::channel();
+        _ = self.jobs.send(Box::new(move || _ = result.send(task())));
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pool;
+    use std::sync::{Arc, Barrier};
+
+    #[test]
+    fn test_results() {
+        let pool = Pool::new(2);
+        let results: Vec<_> = (0..10).map(|n| pool.submit(move || n * n)).collect();
+        let squares: Vec<i32> = results
+            .iter()
+            .map(|result| result.recv().unwrap())
+            .collect();
+        assert_eq!(vec![0, 1, 4, 9, 16, 25, 36, 49, 64, 81], squares);
+    }
+
+    #[test]
+    fn test_parallel() {
+        // Both tasks wait for each other, so this only finishes if they run at the same time.
+        let pool = Pool::new(2);
+        let barrier = Arc::new(Barrier::new(2));
+        let results: Vec<_> = (0..2)
+            .map(|_| {
+                let barrier = Arc::clone(&barrier);
+                pool.submit(move || barrier.wait().is_leader())
+            })
+            .collect();
+        let leaders = results
+            .iter()
+            .filter(|result| result.recv().unwrap())
+            .count();
+        assert_eq!(1, leaders);
+    }
+
+    #[test]
+    fn test_panic() {
+        let pool = Pool::new(1);
+        assert!(pool.submit(|| panic!("Task failed.")).recv().is_err());
+        assert_eq!(Ok(7), pool.submit(|| 7).recv());
+    }
+}
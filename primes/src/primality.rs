@@ -0,0 +1,370 @@
+//! Primality testing for integers of any size: trial division by small primes, then Miller-Rabin.
+//! Negative numbers, zero and one are not prime.
+
+use crate::deadline::{Deadline, Expired};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+const SMALL_PRIMES: [u32; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+// Miller-Rabin with the first 12 primes as bases is exact for every 64-bit number, and with the
+// first 13 for everything below this bound (Sorenson and Webster, 2015).
+const U64_BASES: usize = 12;
+const BIG_BASES: usize = 13;
+const DETERMINISTIC_BOUND: &str = "3317044064679887385961981";
+// Beyond the bound, this many extra rounds with random bases (each wrongly passing a composite
+// with probability at most 1/4).
+const RANDOM_ROUNDS: usize = 24;
+
+pub fn is_prime(number: &BigInt, deadline: &Deadline) -> Result<bool, Expired> {
+    match number.sign() {
+        Sign::Minus => Ok(false),
+        _ => is_prime_natural(number.magnitude(), deadline),
+    }
+}
+
+/// Miller-Rabin with `rounds` random bases and nothing else, so a composite is wrongly called
+/// prime with probability at most 4^-`rounds`.
+pub fn is_probable_prime(
+    number: &BigInt,
+    rounds: u32,
+    deadline: &Deadline,
+) -> Result<bool, Expired> {
+    let n = match number.sign() {
+        Sign::Minus => return Ok(false),
+        _ => number.magnitude(),
+    };
+    match n.to_u64() {
+        Some(small) if small < 5 => Ok(is_prime_u64(small)),
+        _ if n.is_even() => Ok(false),
+        _ => passes_all(n, (0..rounds).map(|_| random_base(n)), deadline),
+    }
+}
+
+/// The smallest prime greater than `number`.
+pub fn next_prime(number: &BigInt, deadline: &Deadline) -> Result<BigInt, Expired> {
+    let mut candidate = number.clone().max(BigInt::one()) + 1u32;
+    while !is_prime(&candidate, deadline)? {
+        deadline.check()?;
+        candidate += 1u32;
+    }
+    Ok(candidate)
+}
+
+/// The largest prime less than `number`, if there is one.
+pub fn previous_prime(number: &BigInt, deadline: &Deadline) -> Result<Option<BigInt>, Expired> {
+    let mut candidate = number - 1u32;
+    while candidate >= BigInt::from(2u32) {
+        if is_prime(&candidate, deadline)? {
+            return Ok(Some(candidate));
+        }
+        deadline.check()?;
+        candidate -= 1u32;
+    }
+    Ok(None)
+}
+
+/// The prime factors of `n` in ascending order, repeated according to their multiplicity (so
+/// there are none for 1). Small factors by trial division, then Pollard's rho.
+pub fn factorize(n: &BigUint, deadline: &Deadline) -> Result<Vec<BigUint>, Expired> {
+    let mut factors: Vec<BigUint> = vec![];
+    let mut n = n.clone();
+    for prime in SMALL_PRIMES {
+        while !n.is_zero() && (&n % prime).is_zero() {
+            factors.push(BigUint::from(prime));
+            n /= prime;
+        }
+    }
+    let mut pending = match n > BigUint::one() {
+        true => vec![n],
+        false => vec![],
+    };
+    while let Some(n) = pending.pop() {
+        if is_prime_natural(&n, deadline)? {
+            factors.push(n);
+        } else {
+            let factor = pollard_rho(&n, deadline)?;
+            pending.push(&n / &factor);
+            pending.push(factor);
+        }
+    }
+    factors.sort();
+    Ok(factors)
+}
+
+/// A non-trivial factor of the odd composite `n` (with no factors below 100).
+fn pollard_rho(n: &BigUint, deadline: &Deadline) -> Result<BigUint, Expired> {
+    for c in 1u32.. {
+        let step = |x: &BigUint| (x * x + c) % n;
+        let (mut x, mut y) = (BigUint::from(2u32), BigUint::from(2u32));
+        let mut factor = BigUint::one();
+        while factor.is_one() {
+            deadline.check()?;
+            x = step(&x);
+            y = step(&step(&y));
+            let difference = if x > y { &x - &y } else { &y - &x };
+            factor = difference.gcd(n);
+        }
+        if factor != *n {
+            return Ok(factor);
+        }
+    }
+    unreachable!("Some c finds a factor of a composite.")
+}
+
+fn is_prime_natural(n: &BigUint, deadline: &Deadline) -> Result<bool, Expired> {
+    if let Some(n) = n.to_u64() {
+        return Ok(is_prime_u64(n));
+    }
+    if SMALL_PRIMES.iter().any(|&prime| (n % prime).is_zero()) {
+        return Ok(false);
+    }
+    let bases = SMALL_PRIMES[..BIG_BASES].iter().map(|&base| base.into());
+    if !passes_all(n, bases, deadline)? {
+        return Ok(false);
+    }
+    let bound = BigUint::parse_bytes(DETERMINISTIC_BOUND.as_bytes(), 10).expect("A number");
+    if *n < bound {
+        return Ok(true);
+    }
+    passes_all(n, (0..RANDOM_ROUNDS).map(|_| random_base(n)), deadline)
+}
+
+/// Whether `n` passes a round of Miller-Rabin with every base.
+fn passes_all(
+    n: &BigUint,
+    bases: impl Iterator<Item = BigUint>,
+    deadline: &Deadline,
+) -> Result<bool, Expired> {
+    for base in bases {
+        if !miller_rabin(n, &base, deadline)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn is_prime_u64(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for prime in SMALL_PRIMES.map(u64::from) {
+        if n.is_multiple_of(prime) {
+            return n == prime;
+        }
+    }
+    if n < 97 * 97 {
+        return true;
+    }
+    let (d, s) = (
+        (n - 1) >> (n - 1).trailing_zeros(),
+        (n - 1).trailing_zeros(),
+    );
+    let multiply = |a: u64, b: u64| ((a as u128 * b as u128) % n as u128) as u64;
+    SMALL_PRIMES[..U64_BASES].iter().all(|&base| {
+        let mut x = 1u64;
+        let (mut power, mut exponent) = (base as u64, d);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                x = multiply(x, power);
+            }
+            power = multiply(power, power);
+            exponent >>= 1;
+        }
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        (1..s).any(|_| {
+            x = multiply(x, x);
+            x == n - 1
+        })
+    })
+}
+
+/// One round of Miller-Rabin: false if `base` proves the odd number `n` composite.
+fn miller_rabin(n: &BigUint, base: &BigUint, deadline: &Deadline) -> Result<bool, Expired> {
+    let n_minus_one = n - 1u32;
+    let s = n_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_one >> s;
+    let mut x = modpow(base, &d, n, deadline)?;
+    if x.is_one() || x == n_minus_one {
+        return Ok(true);
+    }
+    for _ in 1..s {
+        deadline.check()?;
+        x = &x * &x % n;
+        if x == n_minus_one {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// `base` to the power `exponent`, modulo `n`, by square-and-multiply. Unlike `BigUint::modpow`,
+/// this checks the deadline at each bit, since for a number thousands of digits long one
+/// exponentiation alone takes seconds.
+fn modpow(
+    base: &BigUint,
+    exponent: &BigUint,
+    n: &BigUint,
+    deadline: &Deadline,
+) -> Result<BigUint, Expired> {
+    let mut x = BigUint::one();
+    for bit in (0..exponent.bits()).rev() {
+        deadline.check()?;
+        x = &x * &x % n;
+        if exponent.bit(bit) {
+            x = x * base % n;
+        }
+    }
+    Ok(x % n)
+}
+
+/// A random base between 2 and `n - 2`, for `n` greater than 4.
+pub(crate) fn random_base(n: &BigUint) -> BigUint {
+    let words = (n.bits() / 32 + 2) as usize;
+    let random: Vec<u32> = (0..words)
+        .map(|_| RandomState::new().build_hasher().finish() as u32)
+        .collect();
+    BigUint::new(random) % (n - 3u32) + 2u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{factorize, is_prime, is_probable_prime, modpow, next_prime, previous_prime};
+    use crate::deadline::{Deadline, Expired};
+    use num_bigint::{BigInt, BigUint};
+    use std::time::Duration;
+
+    const NEVER: Deadline = Deadline::never();
+
+    fn prime(text: &str) -> bool {
+        is_prime(&text.parse::<BigInt>().unwrap(), &NEVER).unwrap()
+    }
+
+    #[test]
+    fn test_small() {
+        let primes: Vec<i32> = (-10..100).filter(|&n| prime(&n.to_string())).collect();
+        assert_eq!(
+            vec![
+                2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79,
+                83, 89, 97
+            ],
+            primes
+        );
+    }
+
+    #[test]
+    fn test_u64() {
+        assert!(prime("2147483647"));
+        assert!(!prime("2147483649"));
+        assert!(prime("2305843009213693951"));
+        assert!(prime("18446744073709551557"));
+        // Strong pseudoprimes to the first few bases.
+        assert!(!prime("3215031751"));
+        assert!(!prime("3825123056546413051"));
+    }
+
+    #[test]
+    fn test_beyond_u64() {
+        assert!(prime("18446744073709551629"));
+        assert!(!prime("18446744073709551617"));
+        assert!(prime("618970019642690137449562111"));
+        assert!(!prime("618970019642690137449562113"));
+        // A strong pseudoprime to the first 12 prime bases.
+        assert!(!prime("318665857834031151167461"));
+        assert!(!prime("3317044064679887385961981"));
+        assert!(prime("170141183460469231731687303715884105727"));
+        assert!(!prime("-170141183460469231731687303715884105727"));
+    }
+
+    #[test]
+    fn test_is_probable_prime() {
+        let number = |text: &str| text.parse::<BigInt>().unwrap();
+        assert!(is_probable_prime(&number("2"), 1, &NEVER).unwrap());
+        assert!(!is_probable_prime(&number("4"), 1, &NEVER).unwrap());
+        assert!(!is_probable_prime(&number("-7"), 20, &NEVER).unwrap());
+        assert!(is_probable_prime(&number("18446744073709551629"), 20, &NEVER).unwrap());
+        assert!(!is_probable_prime(&number("3317044064679887385961981"), 20, &NEVER).unwrap());
+    }
+
+    #[test]
+    fn test_next_and_previous() {
+        let number = |n: i64| BigInt::from(n);
+        assert_eq!(number(2), next_prime(&number(-5), &NEVER).unwrap());
+        assert_eq!(number(2), next_prime(&number(1), &NEVER).unwrap());
+        assert_eq!(number(3), next_prime(&number(2), &NEVER).unwrap());
+        assert_eq!(number(11), next_prime(&number(7), &NEVER).unwrap());
+        assert_eq!(
+            "18446744073709551629".parse::<BigInt>().unwrap(),
+            next_prime(&"18446744073709551616".parse().unwrap(), &NEVER).unwrap()
+        );
+        assert_eq!(None, previous_prime(&number(2), &NEVER).unwrap());
+        assert_eq!(Some(number(2)), previous_prime(&number(3), &NEVER).unwrap());
+        assert_eq!(
+            Some(number(7)),
+            previous_prime(&number(11), &NEVER).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_factorize() {
+        let factors = |n: u64| -> Vec<u64> {
+            factorize(&BigUint::from(n), &NEVER)
+                .unwrap()
+                .iter()
+                .map(|factor| factor.try_into().unwrap())
+                .collect()
+        };
+        assert_eq!(Vec::<u64>::new(), factors(1));
+        assert_eq!(vec![2, 2, 3], factors(12));
+        assert_eq!(vec![7919], factors(7919));
+        assert_eq!(vec![101, 101], factors(10_201));
+        assert_eq!(
+            vec![2_147_483_647, 2_147_483_647],
+            factors(4_611_686_014_132_420_609)
+        );
+        assert_eq!(
+            vec![
+                BigUint::from(274_177u32),
+                BigUint::from(67_280_421_310_721u64)
+            ],
+            factorize(&"18446744073709551617".parse().unwrap(), &NEVER).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_modpow() {
+        let number = |text: &str| text.parse::<BigUint>().unwrap();
+        let n = number("170141183460469231731687303715884105727");
+        for (base, exponent) in [("2", "0"), ("3", "1"), ("12345", "98765432109876543210")] {
+            let (base, exponent) = (number(base), number(exponent));
+            assert_eq!(
+                Ok(base.modpow(&exponent, &n)),
+                modpow(&base, &exponent, &n, &NEVER)
+            );
+        }
+        assert_eq!(
+            Ok(BigUint::ZERO),
+            modpow(&number("5"), &number("0"), &number("1"), &NEVER)
+        );
+    }
+
+    #[test]
+    fn test_deadline() {
+        let expired = Deadline::after(Some(Duration::ZERO));
+        // Too large for a u64, so Miller-Rabin checks the deadline.
+        let large: BigInt = "18446744073709551629".parse().unwrap();
+        assert_eq!(Err(Expired), is_prime(&large, &expired));
+        assert_eq!(Err(Expired), next_prime(&large, &expired));
+        assert_eq!(Err(Expired), is_probable_prime(&large, 1, &expired));
+        assert_eq!(
+            Err(Expired),
+            factorize(&BigUint::from(1_000_036_000_099u64), &expired)
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! How closely requests must match their schema. Lenient requests may carry fields their method
+//! does not use, and integers written like `7.0` or `7e0`. Strict requests may not: every field
+//! belongs to the method, and every number is an integer literal (which is stricter than the
+//! specification, where `isPrime` takes any number).
+
+use crate::malformed::Malformed;
+use crate::number;
+use serde_json::Value;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Profile {
+    #[default]
+    Lenient,
+    Strict,
+}
+impl FromStr for Profile {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lenient" => Ok(Self::Lenient),
+            "strict" => Ok(Self::Strict),
+            _ => Err(format!("Unknown profile \"{s}\" (lenient, strict).")),
+        }
+    }
+}
+impl Profile {
+    /// Check `method`'s `params` (its fields, or the same values by position), where `envelope`
+    /// are the other fields allowed alongside them. Unknown methods are left for the caller.
+    pub(crate) fn check(
+        self,
+        method: &str,
+        params: &Value,
+        envelope: &[&str],
+    ) -> Result<(), Malformed> {
+        let (Self::Strict, Some(fields)) = (self, method_fields(method)) else {
+            return Ok(());
+        };
+        let values: Vec<&Value> = match params {
+            Value::Object(params) => {
+                let allowed = |key: &str| fields.contains(&key) || envelope.contains(&key);
+                if let Some(unknown) = params.keys().find(|key| !allowed(key)) {
+                    return Err(Malformed::UnknownField(unknown.clone()));
+                }
+                params.values().collect()
+            }
+            Value::Array(params) => params.iter().collect(),
+            _ => vec![],
+        };
+        for value in values {
+            if let Value::Number(value) = value {
+                let text = value.to_string();
+                if !number::is_integer_literal(&text) {
+                    return Err(Malformed::NotAnIntegerLiteral(text));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fields of each method's request (besides `method`).
+fn method_fields(method: &str) -> Option<&'static [&'static str]> {
+    match method {
+        "isPrime" | "nextPrime" | "previousPrime" | "factorize" | "primeCount" => Some(&["number"]),
+        "isProbablePrime" => Some(&["number", "rounds"]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Profile;
+    use crate::cache::Cache;
+    use crate::process_json;
+
+    fn accepts(profile: Profile, line: &str) -> bool {
+        process_json(line.as_bytes(), &Cache::new(100, 0), None, profile).is_ok()
+    }
+
+    #[test]
+    fn test_accepted() {
+        // (request, accepted when lenient, accepted when strict)
+        let requests = [
+            (r#"{"method":"isPrime","number":7}"#, true, true),
+            (r#"{"method":"isPrime","number":-7}"#, true, true),
+            (
+                r#"{"method":"isPrime","number":123456789012345678901234567890}"#,
+                true,
+                true,
+            ),
+            (r#"{"number":7,"method":"isPrime"}"#, true, true),
+            (r#"{"method":"isPrime","number":7.0}"#, true, false),
+            (r#"{"method":"isPrime","number":7.5}"#, true, false),
+            (r#"{"method":"isPrime","number":7e0}"#, true, false),
+            (r#"{"method":"isPrime","number":70E-1}"#, true, false),
+            (r#"{"method":"isPrime","number":-0.0}"#, true, false),
+            (
+                r#"{"method":"isPrime","number":7,"extra":true}"#,
+                true,
+                false,
+            ),
+            (r#"{"method":"isPrime","number":7,"rounds":5}"#, true, false),
+            (r#"{"method":"nextPrime","number":7.0}"#, true, false),
+            (r#"{"method":"nextPrime","number":7,"id":1}"#, true, false),
+            (
+                r#"{"method":"isProbablePrime","number":7,"rounds":5}"#,
+                true,
+                true,
+            ),
+            (
+                r#"{"method":"isProbablePrime","number":7,"rounds":5.0}"#,
+                false,
+                false,
+            ),
+            (
+                r#"{"method":"isProbablePrime","number":7e0,"rounds":5}"#,
+                true,
+                false,
+            ),
+            (
+                r#"{"method":"primeCount","number":100,"limit":5}"#,
+                true,
+                false,
+            ),
+            (r#"{"method":"factorize","number":12}"#, true, true),
+            (r#"{"method":"factorize","number":7.5}"#, false, false),
+            (r#"{"method":"isPrime","number":"7"}"#, false, false),
+            (r#"{"method":"isPrim","number":7}"#, false, false),
+        ];
+        for (line, lenient, strict) in requests {
+            assert_eq!(lenient, accepts(Profile::Lenient, line), "lenient: {line}");
+            assert_eq!(strict, accepts(Profile::Strict, line), "strict: {line}");
+        }
+    }
+}
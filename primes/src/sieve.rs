@@ -0,0 +1,78 @@
+//! Sieve of Eratosthenes over the odd numbers, for questions about every number up to a limit.
+
+pub struct Sieve {
+    limit: u64,
+    // composite[i] is whether 2i + 1 is composite (1 counts as composite).
+    composite: Vec<bool>,
+}
+impl Sieve {
+    pub fn new(limit: u64) -> Self {
+        let mut composite = vec![false; (limit / 2 + 1) as usize];
+        composite[0] = true;
+        let mut odd = 3;
+        while odd * odd <= limit {
+            if !composite[(odd / 2) as usize] {
+                for multiple in (odd * odd..=limit).step_by(2 * odd as usize) {
+                    composite[(multiple / 2) as usize] = true;
+                }
+            }
+            odd += 2;
+        }
+        Self { limit, composite }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Whether `n` (no larger than the limit) is prime.
+    pub fn is_prime(&self, n: u64) -> bool {
+        match n {
+            2 => true,
+            _ if n.is_multiple_of(2) => false,
+            _ => !self.composite[(n / 2) as usize],
+        }
+    }
+
+    /// How many primes there are up to and including `n` (no larger than the limit).
+    pub fn count(&self, n: u64) -> u64 {
+        if n < 2 {
+            return 0;
+        }
+        let odd = self.composite[..=((n - 1) / 2) as usize]
+            .iter()
+            .filter(|&&composite| !composite)
+            .count() as u64;
+        odd + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sieve;
+
+    #[test]
+    fn test_is_prime() {
+        let sieve = Sieve::new(100);
+        let primes: Vec<u64> = (0..=100).filter(|&n| sieve.is_prime(n)).collect();
+        assert_eq!(
+            vec![
+                2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79,
+                83, 89, 97
+            ],
+            primes
+        );
+    }
+
+    #[test]
+    fn test_count() {
+        let sieve = Sieve::new(1_000_000);
+        assert_eq!(0, sieve.count(1));
+        assert_eq!(1, sieve.count(2));
+        assert_eq!(2, sieve.count(3));
+        assert_eq!(2, sieve.count(4));
+        assert_eq!(25, sieve.count(100));
+        assert_eq!(26, sieve.count(101));
+        assert_eq!(78_498, sieve.count(1_000_000));
+    }
+}
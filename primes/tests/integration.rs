@@ -0,0 +1,447 @@
+//! Integration tests for Prime Time.
+
+#[cfg(test)]
+mod test {
+    use primes::{
+        spawn_for_test, spawn_for_test_with_options, ErrorStyle, Options, Profile, Protocol,
+    };
+    use std::io::Write;
+    use std::thread;
+    use std::time::Duration;
+    use testing::{
+        assert_client_receives_bytes,
+        conformance::{assert_conforms, Problem},
+        connect,
+    };
+
+    const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+    fn hex(text: &str) -> String {
+        testing::u8s_to_hex_str(text.as_bytes())
+    }
+
+    #[test]
+    fn prime() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"method\":\"isPrime\",\"number\":7}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"method\":\"isPrime\",\"prime\":true}\n"),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn not_prime() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"method\":\"isPrime\",\"number\":8.5}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"method\":\"isPrime\",\"prime\":false}\n"),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn large_prime() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"method\":\"isPrime\",\"number\":618970019642690137449562111}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"method\":\"isPrime\",\"prime\":true}\n"),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn negative_prime() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"method\":\"isPrime\",\"number\":-7}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"method\":\"isPrime\",\"prime\":false}\n"),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    /// Send each request in turn on one connection, expecting each response.
+    fn assert_responses(exchanges: &[(&str, &str)]) {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+
+        for (request, response) in exchanges {
+            _ = client.write_all(format!("{request}\n").as_bytes());
+            assert_client_receives_bytes!(client, &hex(&format!("{response}\n")), DEFAULT_TIMEOUT);
+        }
+    }
+
+    #[test]
+    fn next_and_previous_prime() {
+        assert_responses(&[
+            (
+                "{\"method\":\"nextPrime\",\"number\":18446744073709551616}",
+                "{\"method\":\"nextPrime\",\"number\":18446744073709551629}",
+            ),
+            (
+                "{\"method\":\"previousPrime\",\"number\":8}",
+                "{\"method\":\"previousPrime\",\"number\":7}",
+            ),
+            (
+                "{\"method\":\"previousPrime\",\"number\":2}",
+                "{\"method\":\"previousPrime\",\"number\":null}",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn factorize() {
+        assert_responses(&[
+            (
+                "{\"method\":\"factorize\",\"number\":360}",
+                "{\"method\":\"factorize\",\"factors\":[2,2,2,3,3,5]}",
+            ),
+            (
+                "{\"method\":\"factorize\",\"number\":18446744073709551617}",
+                "{\"method\":\"factorize\",\"factors\":[274177,67280421310721]}",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn probable_prime_and_prime_count() {
+        assert_responses(&[
+            (
+                "{\"method\":\"isProbablePrime\",\"number\":2147483647,\"rounds\":10}",
+                "{\"method\":\"isProbablePrime\",\"prime\":true}",
+            ),
+            (
+                "{\"method\":\"primeCount\",\"number\":1000}",
+                "{\"method\":\"primeCount\",\"count\":168}",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn pipelined_responses_stay_in_order() {
+        let server = spawn_for_test_with_options(Options {
+            workers: 4,
+            ..Options::default()
+        });
+        let mut client = connect(server.port);
+
+        // The factorization takes longest, but its response still comes first.
+        _ = client.write_all(
+            b"{\"method\":\"factorize\",\"number\":1000036000099}\n\
+              {\"method\":\"isPrime\",\"number\":7}\n\
+              {\"method\":\"isPrime\",\"number\":8}\n",
+        );
+        assert_client_receives_bytes!(
+            client,
+            &hex(concat!(
+                "{\"method\":\"factorize\",\"factors\":[1000003,1000033]}\n",
+                "{\"method\":\"isPrime\",\"prime\":true}\n",
+                "{\"method\":\"isPrime\",\"prime\":false}\n",
+            )),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn method_without_an_integer() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"method\":\"factorize\",\"number\":7.5}\n");
+        assert_client_receives_bytes!(client, &hex("ERROR"), DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn malformed() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"method\":\"isPrime\"}\n");
+        assert_client_receives_bytes!(client, &hex("ERROR"), DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn descriptive_error() {
+        let server = spawn_for_test_with_options(Options {
+            errors: ErrorStyle::Descriptive,
+            ..Options::default()
+        });
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"method\":\"isPrime\",\"number\":\"7\"}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"error\":\"invalidNumber\",\"message\":\"number must be a number, not a string\"}\n"),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn jsonrpc_stays_connected_after_errors() {
+        let server = spawn_for_test_with_options(Options {
+            protocol: Protocol::JsonRpc,
+            ..Options::default()
+        });
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"isPrim\",\"id\":1}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"jsonrpc\":\"2.0\",\"error\":{\"code\":-32601,\"message\":\"Method not found\"},\"id\":1}\n"),
+            DEFAULT_TIMEOUT
+        );
+        _ = client
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"isPrime\",\"params\":[7],\"id\":2}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"jsonrpc\":\"2.0\",\"result\":{\"method\":\"isPrime\",\"prime\":true},\"id\":2}\n"),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn line_too_long() {
+        let server = spawn_for_test_with_options(Options {
+            max_line_length: 64,
+            ..Options::default()
+        });
+        let mut client = connect(server.port);
+
+        // Answered before any newline arrives.
+        _ = client.write_all(&[b' '; 100]);
+        assert_client_receives_bytes!(client, &hex("ERROR"), DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn jsonrpc_discards_a_line_too_long() {
+        let server = spawn_for_test_with_options(Options {
+            protocol: Protocol::JsonRpc,
+            max_line_length: 64,
+            ..Options::default()
+        });
+        let mut client = connect(server.port);
+
+        _ = client.write_all(&[b' '; 100]);
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"jsonrpc\":\"2.0\",\"error\":{\"code\":-32001,\"message\":\"Request too long\"},\"id\":null}\n"),
+            DEFAULT_TIMEOUT
+        );
+        _ = client.write_all(&[b' '; 100]);
+        _ = client
+            .write_all(b"\n{\"jsonrpc\":\"2.0\",\"method\":\"isPrime\",\"params\":[7],\"id\":2}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"jsonrpc\":\"2.0\",\"result\":{\"method\":\"isPrime\",\"prime\":true},\"id\":2}\n"),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn deadline_exceeded() {
+        let server = spawn_for_test_with_options(Options {
+            errors: ErrorStyle::Descriptive,
+            deadline: Some(Duration::ZERO),
+            ..Options::default()
+        });
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"method\":\"factorize\",\"number\":1000036000099}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"error\":\"deadlineExceeded\",\"message\":\"deadline exceeded before answering\"}\n"),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn deadline_exceeded_by_huge_number() {
+        let server = spawn_for_test_with_options(Options {
+            errors: ErrorStyle::Descriptive,
+            deadline: Some(Duration::from_millis(100)),
+            ..Options::default()
+        });
+        let mut client = connect(server.port);
+
+        // 10^5000 + 7, which has no small factors, so it goes on to Miller-Rabin.
+        let number = format!("1{}7", "0".repeat(4_999));
+        _ = client
+            .write_all(format!("{{\"method\":\"isPrime\",\"number\":{number}}}\n").as_bytes());
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"error\":\"deadlineExceeded\",\"message\":\"deadline exceeded before answering\"}\n"),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn lookups_are_not_stalled_by_other_connections() {
+        let server = spawn_for_test_with_options(Options {
+            workers: 2,
+            ..Options::default()
+        });
+        let mut heavy = connect(server.port);
+        let mut light = connect(server.port);
+
+        // The product of two large primes, which runs until the deadline.
+        let factorize =
+            "{\"method\":\"factorize\",\"number\":1427247692705959880439315947500961989719490561}\n";
+        _ = heavy.write_all(factorize.repeat(8).as_bytes());
+        thread::sleep(Duration::from_millis(50));
+        _ = light.write_all(b"{\"method\":\"isPrime\",\"number\":7}\n");
+        assert_client_receives_bytes!(
+            light,
+            &hex("{\"method\":\"isPrime\",\"prime\":true}\n"),
+            Duration::from_millis(100)
+        );
+    }
+
+    #[test]
+    fn strict_profile() {
+        let strict = Options {
+            errors: ErrorStyle::Descriptive,
+            profile: Profile::Strict,
+            ..Options::default()
+        };
+        let server = spawn_for_test_with_options(strict);
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"method\":\"isPrime\",\"number\":7}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"method\":\"isPrime\",\"prime\":true}\n"),
+            DEFAULT_TIMEOUT
+        );
+        _ = client.write_all(b"{\"method\":\"isPrime\",\"number\":7.0}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"error\":\"invalidNumber\",\"message\":\"number must be written as an integer, not 7.0\"}\n"),
+            DEFAULT_TIMEOUT
+        );
+
+        let mut client = connect(server.port);
+        _ = client.write_all(b"{\"method\":\"isPrime\",\"number\":7,\"extra\":1}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"error\":\"unknownField\",\"message\":\"unknown field \\\"extra\\\"\"}\n"),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    /// A request or response in a length-prefixed frame.
+    fn frame(payload: Vec<u8>) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes().to_vec();
+        frame.extend(payload);
+        frame
+    }
+
+    #[test]
+    fn messagepack() {
+        let server = spawn_for_test_with_options(Options {
+            binary: true,
+            ..Options::default()
+        });
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"encoding\":\"msgpack\"}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"encoding\":\"msgpack\"}\n"),
+            DEFAULT_TIMEOUT
+        );
+        let request = serde_json::json!({"method": "isPrime", "number": 7});
+        _ = client.write_all(&frame(rmp_serde::to_vec_named(&request).unwrap()));
+        let response = serde_json::json!({"method": "isPrime", "prime": true});
+        assert_client_receives_bytes!(
+            client,
+            &testing::u8s_to_hex_str(&frame(rmp_serde::to_vec_named(&response).unwrap())),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn cbor() {
+        let server = spawn_for_test_with_options(Options {
+            binary: true,
+            ..Options::default()
+        });
+        let mut client = connect(server.port);
+        let cbor = |value: serde_json::Value| {
+            let mut payload = vec![];
+            ciborium::into_writer(&value, &mut payload).unwrap();
+            frame(payload)
+        };
+
+        _ = client.write_all(b"{\"encoding\":\"cbor\"}\n");
+        assert_client_receives_bytes!(client, &hex("{\"encoding\":\"cbor\"}\n"), DEFAULT_TIMEOUT);
+        // Integers beyond 64 bits come back as strings.
+        let request = serde_json::json!({"method": "nextPrime", "number": 18446744073709551615u64});
+        _ = client.write_all(&cbor(request));
+        let response = serde_json::json!({"method": "nextPrime", "number": "18446744073709551629"});
+        assert_client_receives_bytes!(
+            client,
+            &testing::u8s_to_hex_str(&cbor(response)),
+            DEFAULT_TIMEOUT
+        );
+        // And may be sent as strings.
+        let request = serde_json::json!({"method": "isPrime", "number": "18446744073709551629"});
+        _ = client.write_all(&cbor(request));
+        let response = serde_json::json!({"method": "isPrime", "prime": true});
+        assert_client_receives_bytes!(
+            client,
+            &testing::u8s_to_hex_str(&cbor(response)),
+            DEFAULT_TIMEOUT
+        );
+        _ = client.write_all(&cbor(serde_json::json!({"method": "isPrim", "number": 7})));
+        assert_client_receives_bytes!(
+            client,
+            &testing::u8s_to_hex_str(&cbor(serde_json::json!("ERROR"))),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn binary_needs_enabling() {
+        let server = spawn_for_test();
+        let mut client = connect(server.port);
+
+        _ = client.write_all(b"{\"encoding\":\"msgpack\"}\n");
+        assert_client_receives_bytes!(client, &hex("ERROR"), DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn binary_needs_spec_protocol() {
+        let server = spawn_for_test_with_options(Options {
+            protocol: Protocol::JsonRpc,
+            binary: true,
+            ..Options::default()
+        });
+        let mut client = connect(server.port);
+
+        // Not a negotiation, so just a request without a method.
+        _ = client.write_all(b"{\"encoding\":\"msgpack\"}\n");
+        assert_client_receives_bytes!(
+            client,
+            &hex("{\"jsonrpc\":\"2.0\",\"error\":{\"code\":-32600,\"message\":\"Invalid Request\"},\"id\":null}\n"),
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn conformance() {
+        let server = spawn_for_test();
+        assert_conforms(Problem::PrimeTime, server.port);
+    }
+}
@@ -0,0 +1,77 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use speed::{
+    bench::{parse, process_report},
+    spawn_for_test, Application,
+};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+fn parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("speed/parser");
+    let messages: [(&str, &[u8]); 4] = [
+        (
+            "plate",
+            &[0x20, 0x04, 0x55, 0x4e, 0x31, 0x58, 0x00, 0x00, 0x03, 0xe8],
+        ),
+        ("camera", &[0x80, 0x00, 0x42, 0x00, 0x64, 0x00, 0x3c]),
+        (
+            "dispatcher",
+            &[0x81, 0x03, 0x00, 0x42, 0x01, 0x70, 0x13, 0x88],
+        ),
+        ("incomplete", &[0x20, 0x04, 0x55, 0x4e]),
+    ];
+    for (name, message) in messages {
+        group.bench_function(name, |b| b.iter(|| parse(black_box(message))));
+    }
+    group.finish();
+}
+
+fn reports(c: &mut Criterion) {
+    let mut application = Application::new();
+    let mut car = 0u32;
+    // Each car is seen twice, going ten miles in five minutes (120mph) on a 60mph road.
+    c.bench_function("speed/process_report", |b| {
+        b.iter(|| {
+            car += 1;
+            let plate = car.to_be_bytes();
+            process_report(&mut application, &plate, car, 66, 0, 60);
+            process_report(&mut application, &plate, car + 300, 66, 10, 60)
+        })
+    });
+}
+
+/// Tickets issued end to end over real TCP connections, through the server's own accept loop.
+fn loopback(c: &mut Criterion) {
+    let server = spawn_for_test();
+    let connect = |hello: &[u8]| {
+        let mut stream = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        stream.set_nodelay(true).unwrap();
+        stream.write_all(hello).unwrap();
+        stream
+    };
+    // Two cameras ten miles apart on road 66 (limit 60mph), and its dispatcher.
+    let mut first = connect(&[0x80, 0x00, 0x42, 0x00, 0x00, 0x00, 0x3c]);
+    let mut second = connect(&[0x80, 0x00, 0x42, 0x00, 0x0a, 0x00, 0x3c]);
+    let mut dispatcher = connect(&[0x81, 0x01, 0x00, 0x42]);
+    let mut car = 0u32;
+    // A ticket for an 8-character plate.
+    let mut ticket = [0u8; 26];
+
+    c.bench_function("speed/loopback", |b| {
+        b.iter(|| {
+            // Each car is new, so that none has already had a ticket that day.
+            car += 1;
+            let plate = format!("{car:08}");
+            for (camera, timestamp) in [(&mut first, 0u32), (&mut second, 300)] {
+                let mut message = vec![0x20, 8];
+                message.extend_from_slice(plate.as_bytes());
+                message.extend_from_slice(&timestamp.to_be_bytes());
+                camera.write_all(&message).unwrap();
+            }
+            dispatcher.read_exact(&mut ticket).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, parser, reports, loopback);
+criterion_main!(benches);
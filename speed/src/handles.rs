@@ -2,10 +2,10 @@ use crate::{
     io::{ClientInput, Message, ServerOutput},
     parser, utils,
 };
-use common::{BUFFER_SIZE, THREAD_SLOW_DOWN};
+use common::{Clock, BUFFER_SIZE, THREAD_SLOW_DOWN};
 use std::io::{ErrorKind, Read};
 use std::net::{Shutdown, TcpStream};
-use std::sync::mpsc::Sender;
+use std::sync::{mpsc::Sender, Arc};
 use std::thread;
 use uuid::Uuid;
 
@@ -60,9 +60,17 @@ pub(crate) fn connection(id: Uuid, mut stream: TcpStream, transmitter: Sender<Me
     });
 }
 
-pub(crate) fn heartbeat(mut stream: TcpStream, interval: std::time::Duration) {
+pub(crate) fn heartbeat(
+    mut stream: TcpStream,
+    interval: std::time::Duration,
+    clock: Arc<dyn Clock>,
+) {
+    // Schedule against deadlines rather than sleeping for the interval each time, so that time
+    // spent writing doesn't accumulate as drift.
+    let mut next = clock.now();
     'heartbeat: loop {
-        thread::sleep(interval);
+        next += interval;
+        clock.sleep_until(next);
         if !ServerOutput::Heartbeat.write(&mut stream) {
             _ = stream.shutdown(Shutdown::Both);
             break 'heartbeat;
@@ -8,11 +8,13 @@ use crate::{
     io::{ClientInput, Message, ServerError, ServerOutput},
     models::{Camera, Client, Connection, Dispatcher, Report, Ticket},
 };
-use common::THREAD_SLOW_DOWN;
+use common::{
+    spawn_tcp_for_test, Clock, ServerHandle, ShutdownSignal, SystemClock, THREAD_SLOW_DOWN,
+};
 use std::{
     collections::HashMap,
     net::{Shutdown, TcpListener},
-    sync::mpsc,
+    sync::{mpsc, Arc},
     thread,
     time::Duration,
 };
@@ -34,21 +36,41 @@ pub(crate) type PlateNumber = Vec<u8>;
 pub(crate) type BufferMatch = Result<Option<(ClientInput, usize)>, ()>;
 pub(crate) type IssuedTickets = HashMap<Vec<u8>, Vec<u32>>;
 
-#[derive(Default)]
 pub struct Application {
     connections: HashMap<Uuid, Connection>,
     pending_tickets: HashMap<u16, Vec<Ticket>>,
     reports: HashMap<PlateNumber, Report>,
     days_issued: IssuedTickets,
+    clock: Arc<dyn Clock>,
+}
+impl Default for Application {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
 }
 impl Application {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn run(mut self, listener: TcpListener) -> ! {
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            connections: HashMap::new(),
+            pending_tickets: HashMap::new(),
+            reports: HashMap::new(),
+            days_issued: IssuedTickets::new(),
+            clock,
+        }
+    }
+
+    pub fn run(self, listener: TcpListener) -> ! {
+        self.serve(listener, &ShutdownSignal::new());
+        unreachable!("Server stopped without being asked to shut down.");
+    }
+
+    pub fn serve(mut self, listener: TcpListener, shutdown: &ShutdownSignal) {
         let (conn_tx, conn_rx) = mpsc::channel::<Message>();
-        loop {
+        while !shutdown.is_triggered() {
             // Accept connection.
             if let Ok((stream, addr)) = listener.accept() {
                 let connection = Connection::new(stream);
@@ -124,7 +146,10 @@ impl Application {
                             return;
                         };
                         let interval = Duration::from_millis((deciseconds as u64) * 100);
-                        thread::spawn(move || handles::heartbeat(heartbeat_stream, interval));
+                        let clock = self.clock.clone();
+                        thread::spawn(move || {
+                            handles::heartbeat(heartbeat_stream, interval, clock)
+                        });
                     }
                     connection.heartbeat = Some(deciseconds);
                 }
@@ -196,7 +221,7 @@ impl Application {
         }
         self.pending_tickets
             .entry(ticket.road)
-            .or_insert(Vec::new())
+            .or_default()
             .push(ticket);
     }
 
@@ -213,3 +238,58 @@ impl Application {
         }
     }
 }
+
+pub fn spawn_for_test() -> ServerHandle {
+    spawn_for_test_with_clock(Arc::new(SystemClock))
+}
+
+pub fn spawn_for_test_with_clock(clock: Arc<dyn Clock>) -> ServerHandle {
+    spawn_tcp_for_test(|listener, shutdown| {
+        Application::with_clock(clock).serve(listener, &shutdown)
+    })
+}
+
+/// Message parsing and camera reports, for `benches/speed.rs`.
+#[cfg(feature = "bench")]
+pub mod bench {
+    use crate::{models::Report, Application};
+
+    /// Parse the first message in a client's stream, returning its length if it is complete.
+    pub fn parse(data: &[u8]) -> Option<usize> {
+        crate::parser::nom(data)
+            .ok()
+            .flatten()
+            .map(|(_, length)| length)
+    }
+
+    /// Record a camera's sighting of a car, returning whether it earned the car a ticket.
+    pub fn process_report(
+        application: &mut Application,
+        plate: &[u8],
+        timestamp: u32,
+        road: u16,
+        mile: u16,
+        limit: u16,
+    ) -> bool {
+        application
+            .process_report(Report::new(plate.to_vec(), timestamp, road, mile, limit))
+            .is_some()
+    }
+}
+
+/// The client message parser, for the `speed_parser` fuzz target.
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing {
+    /// Parse a client's stream the way `handles::connection` does, until it is incomplete or invalid.
+    pub fn parse(data: &[u8]) {
+        let mut queue = data;
+        while let Ok(Some((_, drain))) = crate::parser::nom(queue) {
+            assert!(
+                drain > 0 && drain <= queue.len(),
+                "Drained {drain} of {} bytes.",
+                queue.len()
+            );
+            queue = &queue[drain..];
+        }
+    }
+}
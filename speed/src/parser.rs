@@ -64,6 +64,73 @@ pub(crate) fn nom(input: &[u8]) -> BufferMatch {
 mod tests {
     use super::nom;
     use crate::io::ClientInput;
+    use crate::{
+        MESSAGE_TYPE_AM_CAMERA, MESSAGE_TYPE_AM_DISPATCHER, MESSAGE_TYPE_PLATE,
+        MESSAGE_TYPE_WANT_HEARTBEAT,
+    };
+    use proptest::prelude::*;
+
+    fn encode(input: &ClientInput) -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![];
+        match input {
+            ClientInput::Plate(plate, timestamp) => {
+                bytes.push(MESSAGE_TYPE_PLATE);
+                bytes.push(plate.len() as u8);
+                bytes.extend_from_slice(plate);
+                bytes.extend_from_slice(&timestamp.to_be_bytes());
+            }
+            ClientInput::WantHeartbeat(interval) => {
+                bytes.push(MESSAGE_TYPE_WANT_HEARTBEAT);
+                bytes.extend_from_slice(&interval.to_be_bytes());
+            }
+            ClientInput::IAmCamera(road, mile, limit) => {
+                bytes.push(MESSAGE_TYPE_AM_CAMERA);
+                bytes.extend_from_slice(&road.to_be_bytes());
+                bytes.extend_from_slice(&mile.to_be_bytes());
+                bytes.extend_from_slice(&limit.to_be_bytes());
+            }
+            ClientInput::IAmDispatcher(roads) => {
+                bytes.push(MESSAGE_TYPE_AM_DISPATCHER);
+                bytes.push(roads.len() as u8);
+                roads
+                    .iter()
+                    .for_each(|road| bytes.extend_from_slice(&road.to_be_bytes()));
+            }
+            ClientInput::StreamEnded | ClientInput::StreamErrored => {
+                unreachable!("Not sent over the wire.")
+            }
+        }
+        bytes
+    }
+
+    // Only the variants a client can actually send.
+    fn client_input() -> impl Strategy<Value = ClientInput> {
+        prop_oneof![
+            (prop::collection::vec(any::<u8>(), 0..=255), any::<u32>())
+                .prop_map(|(plate, timestamp)| ClientInput::Plate(plate, timestamp)),
+            any::<u32>().prop_map(ClientInput::WantHeartbeat),
+            (any::<u16>(), any::<u16>(), any::<u16>())
+                .prop_map(|(road, mile, limit)| ClientInput::IAmCamera(road, mile, limit)),
+            prop::collection::vec(any::<u16>(), 0..=255).prop_map(ClientInput::IAmDispatcher),
+        ]
+    }
+
+    // Parse a stream delivered in chunks, the same way `handles::connection` buffers it.
+    fn parse_in_chunks(bytes: &[u8], chunk_size: usize) -> Result<Vec<ClientInput>, ()> {
+        let mut queue: Vec<u8> = vec![];
+        let mut parsed: Vec<ClientInput> = vec![];
+        for chunk in bytes.chunks(chunk_size) {
+            queue.extend_from_slice(chunk);
+            while let Some((input, drain)) = nom(&queue)? {
+                parsed.push(input);
+                queue.drain(..drain);
+            }
+        }
+        match queue.is_empty() {
+            true => Ok(parsed),
+            false => Err(()),
+        }
+    }
 
     #[test]
     fn test_invalid() {
@@ -120,4 +187,48 @@ mod tests {
             nom(&[0x81u8, 0x02, 0x03, 0x11, 0xab, 0x9d, 0x00, 0x64, 0x12])
         );
     }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(input in client_input()) {
+            let bytes = encode(&input);
+            let length = bytes.len();
+            prop_assert_eq!(Ok(Some((input, length))), nom(&bytes));
+        }
+
+        #[test]
+        fn test_round_trip_with_trailing_bytes(
+            input in client_input(),
+            trailing in prop::collection::vec(any::<u8>(), 0..16),
+        ) {
+            let mut bytes = encode(&input);
+            let length = bytes.len();
+            bytes.extend_from_slice(&trailing);
+            prop_assert_eq!(Ok(Some((input, length))), nom(&bytes));
+        }
+
+        #[test]
+        fn test_every_prefix_is_incomplete(input in client_input()) {
+            let bytes = encode(&input);
+            for split in 0..bytes.len() {
+                prop_assert_eq!(Ok(None), nom(&bytes[..split]));
+            }
+        }
+
+        #[test]
+        fn test_split_anywhere(
+            inputs in prop::collection::vec(client_input(), 1..8),
+            chunk_size in 1usize..64,
+        ) {
+            let bytes: Vec<u8> = inputs.iter().flat_map(encode).collect();
+            prop_assert_eq!(Ok(inputs), parse_in_chunks(&bytes, chunk_size));
+        }
+
+        #[test]
+        fn test_garbage_does_not_panic(bytes in prop::collection::vec(any::<u8>(), 0..1_024)) {
+            if let Ok(Some((_, drain))) = nom(&bytes) {
+                prop_assert!(drain > 0 && drain <= bytes.len());
+            }
+        }
+    }
 }
@@ -1,4 +1,4 @@
-/**
+/*!
  * Integration tests for Speed Daemon.
  * Unit test belong at the bottom of source files.
  *
@@ -8,37 +8,57 @@
 
 #[cfg(test)]
 mod test {
-    use speed::Application;
-    use std::thread;
+    use speed::{spawn_for_test, spawn_for_test_with_clock};
+    use std::sync::Arc;
     use std::time::Duration;
     use testing::{
-        assert_client_receives_bytes, connect, listen_on_available_port, send_bytes_from,
+        assert_client_receives_bytes, assert_snapshot,
+        clock::TestClock,
+        conformance::{assert_conforms, Problem},
+        connect,
+        fault::{FaultInjector, Fragment},
+        hex_str_to_u8s, send_bytes_from, send_bytes_with_faults,
+        snapshot::{Conversation, Format},
+        trace::Trace,
     };
 
     const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
 
-    fn setup() -> u16 {
-        let (listener, port) = listen_on_available_port();
-        thread::spawn(move || Application::new().run(listener));
-        port
+    #[test]
+    fn heartbeat() {
+        let clock = Arc::new(TestClock::new());
+        let server = spawn_for_test_with_clock(clock.clone());
+        let mut client = connect(server.port);
+
+        send_bytes_from!(client, "40 00 00 00 0a");
+        assert!(clock.wait_for_sleepers(1, DEFAULT_TIMEOUT));
+        clock.advance(Duration::from_secs(1));
+        assert_client_receives_bytes!(client, "41", DEFAULT_TIMEOUT);
+
+        // Every interval that passes gets its own heartbeat, even if time jumps.
+        clock.advance(Duration::from_secs(3));
+        assert_client_receives_bytes!(client, "41 41 41", DEFAULT_TIMEOUT);
     }
 
     #[test]
-    #[ignore]
-    fn heartbeat() {
-        let port = setup();
-        let mut client = connect(port);
+    #[should_panic]
+    fn heartbeat_not_early() {
+        let clock = Arc::new(TestClock::new());
+        let server = spawn_for_test_with_clock(clock.clone());
+        let mut client = connect(server.port);
 
         send_bytes_from!(client, "40 00 00 00 0a");
-        assert_client_receives_bytes!(client, "41", Duration::from_millis(1100));
+        assert!(clock.wait_for_sleepers(1, DEFAULT_TIMEOUT));
+        clock.advance(Duration::from_millis(999));
+        assert_client_receives_bytes!(client, "41", Duration::from_millis(100));
     }
 
     #[test]
     fn car() {
-        let port = setup();
-        let mut camera_one = connect(port);
-        let mut camera_two = connect(port);
-        let mut dispatcher = connect(port);
+        let server = spawn_for_test();
+        let mut camera_one = connect(server.port);
+        let mut camera_two = connect(server.port);
+        let mut dispatcher = connect(server.port);
 
         // send_bytes_from!(camera_one, "80 03 11 0c 9d 00 64");
         {
@@ -105,19 +125,19 @@ mod test {
     #[test]
     #[ignore]
     fn multiple_tickets() {
-        let port = setup();
+        let server = spawn_for_test();
 
-        let mut broken_camera = connect(port);
+        let mut broken_camera = connect(server.port);
         send_bytes_from!(broken_camera, "80 00 00");
 
-        let mut first_camera = connect(port);
+        let mut first_camera = connect(server.port);
         send_bytes_from!(first_camera, "80 1a 47 0d 18 00 50");
-        let mut second_camera = connect(port);
+        let mut second_camera = connect(server.port);
         send_bytes_from!(second_camera, "80 1a 47 0d 23 00 50");
-        let mut third_camera = connect(port);
+        let mut third_camera = connect(server.port);
         send_bytes_from!(third_camera, "80 1a 47 0d 2e 00 50");
 
-        let mut dispatcher = connect(port);
+        let mut dispatcher = connect(server.port);
         send_bytes_from!(dispatcher, "81 01");
 
         send_bytes_from!(second_camera, "20 07 52 56 36 30 55 58 50 02 16 d0 8f");
@@ -135,10 +155,10 @@ mod test {
     #[test]
     #[ignore]
     fn multiple_cars() {
-        let port = setup();
-        let mut first_camera = connect(port);
-        let mut second_camera = connect(port);
-        let mut dispatcher = connect(port);
+        let server = spawn_for_test();
+        let mut first_camera = connect(server.port);
+        let mut second_camera = connect(server.port);
+        let mut dispatcher = connect(server.port);
 
         send_bytes_from!(first_camera, "80 a7 22 00 0a 00 3c");
         send_bytes_from!(second_camera, "80 a7 22 04 ca 00 3c");
@@ -165,4 +185,66 @@ mod test {
             DEFAULT_TIMEOUT
         );
     }
+
+    #[test]
+    fn replay_speed_log() {
+        let server = spawn_for_test();
+        let trace =
+            Trace::parse(include_str!("../speed.log")).expect("Invalid trace in speed.log.");
+
+        // Heartbeats depend on timing rather than input, so don't compare them.
+        let diff = trace.replay(server.port, &[&[0x41]], Duration::from_secs(2));
+        assert!(diff.is_empty(), "Server output differs from trace:\n{diff}");
+    }
+
+    #[test]
+    fn car_fragmented() {
+        let server = spawn_for_test();
+        let mut camera_one = connect(server.port);
+        let mut camera_two = connect(server.port);
+        let mut dispatcher = connect(server.port);
+        let mut faults = FaultInjector::new(Fragment::Random).with_jitter(Duration::from_millis(1));
+
+        // Declare each client type and report the plate in a single packet, but split randomly.
+        faults.send_coalesced(
+            &mut camera_one,
+            &[
+                &hex_str_to_u8s("80 03 11 0c 9d 00 64").unwrap(),
+                &hex_str_to_u8s("20 07 56 48 30 30 4a 52 57 00 0a 61 0d").unwrap(),
+            ],
+        );
+        send_bytes_with_faults!(faults, dispatcher, "81 01 03 11");
+        let mut faults = FaultInjector::new(Fragment::ByteByByte);
+        send_bytes_with_faults!(faults, camera_two, "80 03 11 0c a7 00 64");
+        send_bytes_with_faults!(faults, camera_two, "20 07 56 48 30 30 4a 52 57 00 0a 62 39");
+
+        assert_client_receives_bytes!(
+            dispatcher,
+            "21 07 56 48 30 30 4a 52 57 03 11 0c 9d 00 0a 61 0d 0c a7 00 0a 62 39 2e e0",
+            DEFAULT_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn conformance() {
+        let server = spawn_for_test();
+        assert_conforms(Problem::SpeedDaemon, server.port);
+    }
+
+    #[test]
+    fn ticket_snapshot() {
+        let server = spawn_for_test();
+        let mut conversation = Conversation::new(server.port, Format::Hex);
+        let mut send = |client: &str, hex: &str| {
+            conversation.send(client, &hex_str_to_u8s(hex).expect("Valid hex"));
+        };
+        send("camera-one", "80 03 11 0c 9d 00 64");
+        send("camera-two", "80 03 11 0c a7 00 64");
+        send("dispatcher", "81 01 03 11");
+        send("camera-one", "20 07 56 48 30 30 4a 52 57 00 0a 61 0d");
+        send("camera-two", "20 07 56 48 30 30 4a 52 57 00 0a 62 39");
+        // Neither a camera nor a dispatcher may declare itself twice.
+        send("camera-one", "80 03 11 0c 9d 00 64");
+        assert_snapshot!(conversation, "ticket");
+    }
 }
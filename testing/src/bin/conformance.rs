@@ -0,0 +1,26 @@
+//! Usage: conformance <smoke|primes|means|chat|database|mob|speed> [address]
+
+use std::env;
+use std::process::ExitCode;
+use testing::conformance::{run, Problem};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8096";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    let problem: Problem = match args.get(1).map(|problem| problem.parse()) {
+        Some(Ok(problem)) => problem,
+        Some(Err(err)) => panic!("{err}"),
+        None => panic!("Usage: conformance <smoke|primes|means|chat|database|mob|speed> [address]"),
+    };
+    let address = args.get(2).map(String::as_str).unwrap_or(DEFAULT_ADDRESS);
+
+    let results = run(problem, address);
+    for result in &results {
+        print!("{result}");
+    }
+    match results.iter().all(|result| result.passed()) {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
@@ -0,0 +1,39 @@
+//! Usage: loadgen <echo|primes|keystore|speed> [address] [connections] [seconds]
+
+use std::env;
+use std::time::Duration;
+use testing::load::{run, LoadConfig, Profile};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8096";
+const DEFAULT_CONNECTIONS: usize = 10;
+const DEFAULT_SECONDS: u64 = 10;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let profile: Profile = match args.get(1).map(|profile| profile.parse()) {
+        Some(Ok(profile)) => profile,
+        Some(Err(err)) => panic!("{err}"),
+        None => {
+            panic!("Usage: loadgen <echo|primes|keystore|speed> [address] [connections] [seconds]")
+        }
+    };
+    let config = LoadConfig {
+        profile,
+        address: args.get(2).cloned().unwrap_or(DEFAULT_ADDRESS.to_string()),
+        connections: match args.get(3) {
+            Some(connections) => connections.parse().expect("Invalid number of connections."),
+            None => DEFAULT_CONNECTIONS,
+        },
+        duration: Duration::from_secs(match args.get(4) {
+            Some(seconds) => seconds.parse().expect("Invalid number of seconds."),
+            None => DEFAULT_SECONDS,
+        }),
+        progress: true,
+    };
+
+    println!(
+        "Running {:?} load against {} with {} connections for {:?}...",
+        config.profile, config.address, config.connections, config.duration
+    );
+    print!("{}", run(&config));
+}
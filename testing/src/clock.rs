@@ -0,0 +1,94 @@
+//! A clock that only moves when a test tells it to.
+
+use common::Clock;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct State {
+    elapsed: Duration,
+    sleepers: usize,
+}
+
+pub struct TestClock {
+    start: Instant,
+    state: Mutex<State>,
+    changed: Condvar,
+}
+impl Default for TestClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl TestClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Move time forward, waking any thread whose sleep has now finished.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state.lock().expect("Test clock poisoned.");
+        state.elapsed += duration;
+        self.changed.notify_all();
+    }
+
+    /// Wait (in real time) until at least `count` threads are sleeping on this clock, so a test
+    /// can be sure the server is waiting before it advances time. Returns false on timeout.
+    pub fn wait_for_sleepers(&self, count: usize, timeout: Duration) -> bool {
+        let state = self.state.lock().expect("Test clock poisoned.");
+        let (_state, result) = self
+            .changed
+            .wait_timeout_while(state, timeout, |state| state.sleepers < count)
+            .expect("Test clock poisoned.");
+        !result.timed_out()
+    }
+}
+impl Clock for TestClock {
+    fn now(&self) -> Instant {
+        self.start + self.state.lock().expect("Test clock poisoned.").elapsed
+    }
+
+    fn sleep_until(&self, deadline: Instant) {
+        let mut state = self.state.lock().expect("Test clock poisoned.");
+        state.sleepers += 1;
+        self.changed.notify_all();
+        while self.start + state.elapsed < deadline {
+            state = self.changed.wait(state).expect("Test clock poisoned.");
+        }
+        state.sleepers -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TestClock;
+    use common::Clock;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_advance() {
+        let clock = TestClock::new();
+        let before = clock.now();
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(Duration::from_secs(60), clock.now() - before);
+    }
+
+    #[test]
+    fn test_sleep_wakes_on_advance() {
+        let clock = Arc::new(TestClock::new());
+        let sleeper = clock.clone();
+        let thread = thread::spawn(move || sleeper.sleep(Duration::from_secs(3600)));
+
+        assert!(clock.wait_for_sleepers(1, Duration::from_secs(1)));
+        clock.advance(Duration::from_secs(1800));
+        assert!(clock.wait_for_sleepers(1, Duration::from_millis(10)));
+        clock.advance(Duration::from_secs(1800));
+        thread.join().expect("Sleeping thread panicked.");
+    }
+}
@@ -0,0 +1,102 @@
+use super::{Check, Client, Outcome, Session};
+
+pub(super) const CHECKS: &[Check] = &[
+    ("lists users already in the room", room_listing),
+    ("announces joins", announces_joins),
+    ("broadcasts messages to everyone else", broadcasts_messages),
+    ("announces leaves", announces_leaves),
+    ("accepts 16 character names", long_names),
+    ("rejects invalid names", rejects_invalid_names),
+    ("users who haven't joined receive nothing", unjoined_users),
+];
+
+/// Connect and join the room, returning the client and the room's presence notification.
+pub(super) fn join(session: &Session, name: &str) -> Result<(Client, String), String> {
+    let mut client = session.connect(name)?;
+    client.read_line()?;
+    client.send_line(name);
+    let presence = client.read_line()?;
+    match presence.starts_with('*') {
+        true => Ok((client, presence)),
+        false => Err(format!(
+            "{name} expected a presence notification, but received {presence:?}"
+        )),
+    }
+}
+
+pub(super) fn expect_announcement(client: &mut Client, containing: &str) -> Outcome {
+    let line = client.read_line()?;
+    match line.starts_with('*') && line.contains(containing) {
+        true => Ok(()),
+        false => Err(format!(
+            "Expected an announcement about {containing}, but received {line:?}"
+        )),
+    }
+}
+
+fn room_listing(session: &Session) -> Outcome {
+    let (_alice, presence) = join(session, "alice")?;
+    if presence.contains("alice") {
+        return Err(format!(
+            "alice should not be listed to herself: {presence:?}"
+        ));
+    }
+    let (_bob, presence) = join(session, "bob")?;
+    match presence.contains("alice") {
+        true => Ok(()),
+        false => Err(format!("bob should see alice in the room: {presence:?}")),
+    }
+}
+
+fn announces_joins(session: &Session) -> Outcome {
+    let (mut alice, _) = join(session, "alice")?;
+    let (_bob, _) = join(session, "bob")?;
+    expect_announcement(&mut alice, "bob")
+}
+
+fn broadcasts_messages(session: &Session) -> Outcome {
+    let (mut alice, _) = join(session, "alice")?;
+    let (mut bob, _) = join(session, "bob")?;
+    let (mut carol, _) = join(session, "carol")?;
+    expect_announcement(&mut alice, "bob")?;
+    expect_announcement(&mut alice, "carol")?;
+    expect_announcement(&mut bob, "carol")?;
+
+    alice.send_line("Hello, everyone!");
+    bob.expect_line("[alice] Hello, everyone!")?;
+    carol.expect_line("[alice] Hello, everyone!")?;
+    alice.expect_silence()
+}
+
+fn announces_leaves(session: &Session) -> Outcome {
+    let (mut alice, _) = join(session, "alice")?;
+    let (bob, _) = join(session, "bob")?;
+    expect_announcement(&mut alice, "bob")?;
+    drop(bob);
+    expect_announcement(&mut alice, "bob")
+}
+
+fn long_names(session: &Session) -> Outcome {
+    let (mut alice, _) = join(session, "alice")?;
+    let (_long, _) = join(session, "SixteenCharName1")?;
+    expect_announcement(&mut alice, "SixteenCharName1")
+}
+
+fn rejects_invalid_names(session: &Session) -> Outcome {
+    let (mut alice, _) = join(session, "alice")?;
+    for name in ["", "not valid", "bad!"] {
+        let mut client = session.connect("invalid")?;
+        client.read_line()?;
+        client.send_line(name);
+        client.expect_disconnect()?;
+    }
+    alice.expect_silence()
+}
+
+fn unjoined_users(session: &Session) -> Outcome {
+    let (mut alice, _) = join(session, "alice")?;
+    let mut lurker = session.connect("lurker")?;
+    lurker.read_line()?;
+    alice.send_line("Is anyone there?");
+    lurker.expect_silence()
+}
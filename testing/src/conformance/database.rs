@@ -0,0 +1,69 @@
+use super::{Check, Outcome, Session};
+
+pub(super) const CHECKS: &[Check] = &[
+    ("insert and retrieve", insert_and_retrieve),
+    ("inserts overwrite", overwrite),
+    ("values may contain equals signs", equals_in_value),
+    ("keys and values may be empty", empty_key_and_value),
+    ("reports a version", version),
+    ("version cannot be modified", version_is_read_only),
+];
+
+fn insert_and_retrieve(session: &Session) -> Outcome {
+    let client = session.udp("client")?;
+    client.send(b"foo=bar");
+    client.send(b"foo");
+    client.expect(b"foo=bar")
+}
+
+fn overwrite(session: &Session) -> Outcome {
+    let client = session.udp("client")?;
+    client.send(b"foo=bar");
+    client.send(b"foo=baz");
+    client.send(b"foo");
+    client.expect(b"foo=baz")
+}
+
+fn equals_in_value(session: &Session) -> Outcome {
+    let client = session.udp("client")?;
+    client.send(b"foo==bar=baz=");
+    client.send(b"foo");
+    client.expect(b"foo==bar=baz=")
+}
+
+fn empty_key_and_value(session: &Session) -> Outcome {
+    let client = session.udp("client")?;
+    client.send(b"=empty key");
+    client.send(b"");
+    client.expect(b"=empty key")?;
+    client.send(b"empty value=");
+    client.send(b"empty value");
+    client.expect(b"empty value=")
+}
+
+fn version(session: &Session) -> Outcome {
+    let client = session.udp("client")?;
+    client.send(b"version");
+    let response = client
+        .receive(super::RESPONSE_TIMEOUT)
+        .ok_or("Timed out waiting for version")?;
+    match response.strip_prefix(b"version=") {
+        Some(version) if !version.is_empty() => Ok(()),
+        _ => Err(format!(
+            "Invalid version response {:?}",
+            String::from_utf8_lossy(&response)
+        )),
+    }
+}
+
+fn version_is_read_only(session: &Session) -> Outcome {
+    let client = session.udp("client")?;
+    client.send(b"version");
+    let original = client
+        .receive(super::RESPONSE_TIMEOUT)
+        .ok_or("Timed out waiting for version")?;
+    client.send(b"version=modified");
+    client.send(b"version");
+    client.expect(&original)?;
+    client.expect_silence()
+}
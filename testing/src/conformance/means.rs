@@ -0,0 +1,83 @@
+use super::{Check, Client, Outcome, Session};
+
+pub(super) const CHECKS: &[Check] = &[
+    ("example session", example_session),
+    ("no prices in range", empty_range),
+    ("minimum after maximum", inverted_range),
+    ("negative prices", negative_prices),
+    ("large prices don't overflow", large_prices),
+    ("messages split across packets", split_messages),
+    ("sessions are separate", separate_sessions),
+];
+
+fn message(kind: u8, first: i32, second: i32) -> Vec<u8> {
+    let mut message = vec![kind];
+    message.extend_from_slice(&first.to_be_bytes());
+    message.extend_from_slice(&second.to_be_bytes());
+    message
+}
+
+fn insert(client: &mut Client, timestamp: i32, price: i32) {
+    client.send(&message(b'I', timestamp, price));
+}
+
+fn expect_mean(client: &mut Client, min: i32, max: i32, mean: i32) -> Outcome {
+    client.send(&message(b'Q', min, max));
+    client.expect(&mean.to_be_bytes())
+}
+
+fn example_session(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    insert(&mut client, 12345, 101);
+    insert(&mut client, 12346, 102);
+    insert(&mut client, 12347, 100);
+    insert(&mut client, 40960, 5);
+    expect_mean(&mut client, 12288, 16384, 101)
+}
+
+fn empty_range(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    insert(&mut client, 100, 5);
+    expect_mean(&mut client, 200, 300, 0)
+}
+
+fn inverted_range(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    insert(&mut client, 100, 5);
+    expect_mean(&mut client, 200, 0, 0)
+}
+
+fn negative_prices(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    insert(&mut client, -10, -100);
+    insert(&mut client, 10, -200);
+    expect_mean(&mut client, i32::MIN, i32::MAX, -150)
+}
+
+fn large_prices(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    insert(&mut client, 1, i32::MAX);
+    insert(&mut client, 2, i32::MAX);
+    insert(&mut client, 3, i32::MAX);
+    expect_mean(&mut client, 1, 3, i32::MAX)
+}
+
+fn split_messages(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    let mut bytes = message(b'I', 1, 10);
+    bytes.extend(message(b'I', 2, 20));
+    bytes.extend(message(b'Q', 0, 10));
+    for byte in bytes {
+        client.send(&[byte]);
+    }
+    client.expect(&15i32.to_be_bytes())
+}
+
+fn separate_sessions(session: &Session) -> Outcome {
+    let mut first = session.connect("first")?;
+    let mut second = session.connect("second")?;
+    insert(&mut first, 1, 10);
+    insert(&mut second, 1, 30);
+    expect_mean(&mut first, 0, 10, 10)?;
+    expect_mean(&mut second, 0, 10, 30)
+}
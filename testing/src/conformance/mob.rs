@@ -0,0 +1,103 @@
+//! Checks run through the proxy, so every client is both the victim and (via the upstream chat
+//! server) the recipient of rewritten messages.
+
+use super::chat::{expect_announcement, join};
+use super::{Check, Client, Outcome, Session};
+
+const TONY: &str = "7YWHMfk9JZe0LM0g1ZauHuiSxhI";
+
+pub(super) const CHECKS: &[Check] = &[
+    ("proxies the chat room", proxies_chat),
+    ("rewrites a lone address", rewrites_lone_address),
+    (
+        "rewrites addresses anywhere in a message",
+        rewrites_positions,
+    ),
+    ("rewrites multiple addresses", rewrites_multiple),
+    ("leaves non-addresses alone", leaves_non_addresses),
+];
+
+fn pair(session: &Session) -> Result<(Client, Client), String> {
+    // The upstream room may be shared, so pick names unlikely to clash.
+    let suffix = std::process::id() % 10_000;
+    let (mut alice, _) = join(session, &format!("alice{suffix}"))?;
+    let (bob, _) = join(session, &format!("bob{suffix}"))?;
+    expect_announcement(&mut alice, &format!("bob{suffix}"))?;
+    Ok((alice, bob))
+}
+
+fn expect_relayed(
+    sender: &mut Client,
+    recipient: &mut Client,
+    sent: &str,
+    expected: &str,
+) -> Outcome {
+    sender.send_line(sent);
+    let line = recipient.read_line()?;
+    match line.split_once("] ") {
+        Some((_name, message)) if message == expected => Ok(()),
+        _ => Err(format!(
+            "Expected {expected:?} to be relayed, but received {line:?}"
+        )),
+    }
+}
+
+fn proxies_chat(session: &Session) -> Outcome {
+    let (mut alice, mut bob) = pair(session)?;
+    expect_relayed(&mut alice, &mut bob, "Hi bob!", "Hi bob!")?;
+    expect_relayed(&mut bob, &mut alice, "Hi alice!", "Hi alice!")
+}
+
+fn rewrites_lone_address(session: &Session) -> Outcome {
+    let (mut alice, mut bob) = pair(session)?;
+    expect_relayed(&mut alice, &mut bob, "7F1u3wSD5RbOHQmupo9nx4TnhQ", TONY)
+}
+
+fn rewrites_positions(session: &Session) -> Outcome {
+    let (mut alice, mut bob) = pair(session)?;
+    let address = "7iKDZEwPZSqIvDnHvVN2r0hUWXD5rHX";
+    expect_relayed(
+        &mut alice,
+        &mut bob,
+        &format!("{address} is at the start"),
+        &format!("{TONY} is at the start"),
+    )?;
+    expect_relayed(
+        &mut bob,
+        &mut alice,
+        &format!("Send it to {address} please"),
+        &format!("Send it to {TONY} please"),
+    )?;
+    expect_relayed(
+        &mut alice,
+        &mut bob,
+        &format!("At the end is {address}"),
+        &format!("At the end is {TONY}"),
+    )
+}
+
+fn rewrites_multiple(session: &Session) -> Outcome {
+    let (mut alice, mut bob) = pair(session)?;
+    expect_relayed(
+        &mut alice,
+        &mut bob,
+        "Either 7LOrwbDlS8NujgjddyogWgIM93MV5N2VR or 7adNeSwJkMakpEcln9HEtthSRtxdmEHOT8T",
+        &format!("Either {TONY} or {TONY}"),
+    )
+}
+
+fn leaves_non_addresses(session: &Session) -> Outcome {
+    let (mut alice, mut bob) = pair(session)?;
+    for message in [
+        // Too short and too long.
+        "7abcdefghijklmnopqrstuvwx",
+        "7abcdefghijklmnopqrstuvwxyz0123456789",
+        // Doesn't start with a 7.
+        "8F1u3wSD5RbOHQmupo9nx4TnhQ",
+        // Part of a longer word, such as a product ID.
+        "This is a product ID, not a Boguscoin: 7YrZXzfUn5TtjGpPDQ4gV4BYNbuc-1234",
+    ] {
+        expect_relayed(&mut alice, &mut bob, message, message)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,368 @@
+//! Local equivalents of the official Protohackers checks, exercising the edge cases each problem's
+//! specification describes. Failing checks come with a transcript of everything sent and
+//! received, in the annotated format that `trace::Trace` can replay.
+
+mod chat;
+mod database;
+mod means;
+mod mob;
+mod primes;
+mod smoke;
+mod speed;
+
+use crate::u8s_to_hex_str;
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream, UdpSocket};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(1_000);
+const SILENCE_TIMEOUT: Duration = Duration::from_millis(250);
+const READ_BUFFER_SIZE: usize = 4_096;
+
+pub type Outcome = Result<(), String>;
+type Check = (&'static str, fn(&Session) -> Outcome);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Problem {
+    Smoke,
+    PrimeTime,
+    MeansToAnEnd,
+    BudgetChat,
+    UnusualDatabase,
+    MobInTheMiddle,
+    SpeedDaemon,
+}
+impl Problem {
+    fn checks(&self) -> &'static [Check] {
+        match self {
+            Self::Smoke => smoke::CHECKS,
+            Self::PrimeTime => primes::CHECKS,
+            Self::MeansToAnEnd => means::CHECKS,
+            Self::BudgetChat => chat::CHECKS,
+            Self::UnusualDatabase => database::CHECKS,
+            Self::MobInTheMiddle => mob::CHECKS,
+            Self::SpeedDaemon => speed::CHECKS,
+        }
+    }
+}
+impl FromStr for Problem {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "smoke" | "echo" => Ok(Self::Smoke),
+            "primes" => Ok(Self::PrimeTime),
+            "means" | "keystore" => Ok(Self::MeansToAnEnd),
+            "chat" => Ok(Self::BudgetChat),
+            "database" | "db" => Ok(Self::UnusualDatabase),
+            "mob" => Ok(Self::MobInTheMiddle),
+            "speed" => Ok(Self::SpeedDaemon),
+            _ => Err(format!(
+                "Unknown problem \"{s}\" (smoke, primes, means, chat, database, mob, speed)."
+            )),
+        }
+    }
+}
+
+pub struct CheckResult {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub transcript: Transcript,
+}
+impl CheckResult {
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+impl Display for CheckResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.outcome {
+            Ok(()) => writeln!(f, "PASS {}", self.name),
+            Err(reason) => write!(f, "FAIL {}: {reason}\n{}", self.name, self.transcript),
+        }
+    }
+}
+
+/// Run every check for a problem against the server at `address` (such as "127.0.0.1:8096").
+pub fn run(problem: Problem, address: &str) -> Vec<CheckResult> {
+    problem
+        .checks()
+        .iter()
+        .map(|(name, check)| {
+            let session = Session {
+                address: address.to_string(),
+                transcript: Rc::default(),
+            };
+            let outcome = check(&session);
+            CheckResult {
+                name,
+                outcome,
+                transcript: session.transcript.take(),
+            }
+        })
+        .collect()
+}
+
+/// Run every check against a local server, panicking with the transcripts of any that fail.
+pub fn assert_conforms(problem: Problem, port: u16) {
+    let failures = run(problem, &format!("127.0.0.1:{port}"))
+        .into_iter()
+        .filter(|result| !result.passed())
+        .map(|result| result.to_string())
+        .collect::<Vec<_>>();
+    assert!(
+        failures.is_empty(),
+        "{problem:?} conformance failed:\n{}",
+        failures.join("\n")
+    );
+}
+
+#[derive(Debug, Default)]
+pub struct Transcript {
+    lines: Vec<String>,
+}
+impl Transcript {
+    fn record(&mut self, client: &str, sent: bool, bytes: &[u8]) {
+        let text = format!("{:?}", String::from_utf8_lossy(bytes));
+        self.lines.push(match sent {
+            true => format!("({client}) : {}  {text}", u8s_to_hex_str(bytes)),
+            false => format!(">>> {}  ({client}) {text}", u8s_to_hex_str(bytes)),
+        });
+    }
+}
+impl Display for Transcript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.lines.iter().try_for_each(|line| writeln!(f, "{line}"))
+    }
+}
+
+pub struct Session {
+    address: String,
+    transcript: Rc<RefCell<Transcript>>,
+}
+impl Session {
+    fn connect(&self, label: &str) -> Result<Client, String> {
+        let stream = TcpStream::connect(&self.address)
+            .map_err(|err| format!("{label} could not connect: {err}"))?;
+        Ok(Client {
+            label: label.to_string(),
+            stream,
+            pending: vec![],
+            transcript: self.transcript.clone(),
+        })
+    }
+
+    fn udp(&self, label: &str) -> Result<UdpClient, String> {
+        let error = |err| format!("{label} could not open UDP socket: {err}");
+        let socket = UdpSocket::bind(("0.0.0.0", 0)).map_err(error)?;
+        socket.connect(&self.address).map_err(error)?;
+        Ok(UdpClient {
+            label: label.to_string(),
+            socket,
+            transcript: self.transcript.clone(),
+        })
+    }
+}
+
+struct Client {
+    label: String,
+    stream: TcpStream,
+    // Received but not yet expected.
+    pending: Vec<u8>,
+    transcript: Rc<RefCell<Transcript>>,
+}
+impl Client {
+    fn send(&mut self, bytes: &[u8]) {
+        self.transcript
+            .borrow_mut()
+            .record(&self.label, true, bytes);
+        _ = self.stream.write_all(bytes);
+    }
+
+    fn send_line(&mut self, line: &str) {
+        self.send(format!("{line}\n").as_bytes());
+    }
+
+    fn half_close(&mut self) {
+        _ = self.stream.shutdown(Shutdown::Write);
+    }
+
+    /// Read more from the stream into `pending`, returning false on timeout or disconnect.
+    fn fill(&mut self, deadline: Instant) -> Result<bool, String> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(false);
+        }
+        _ = self.stream.set_read_timeout(Some(remaining));
+        let mut buffer = [0u8; READ_BUFFER_SIZE];
+        match self.stream.read(&mut buffer) {
+            Ok(0) => Err(format!("{} was disconnected", self.label)),
+            Ok(n) => {
+                self.transcript
+                    .borrow_mut()
+                    .record(&self.label, false, &buffer[..n]);
+                self.pending.extend_from_slice(&buffer[..n]);
+                Ok(true)
+            }
+            Err(ref e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                Ok(false)
+            }
+            Err(err) => Err(format!("{} errored: {err}", self.label)),
+        }
+    }
+
+    fn read_exact(&mut self, length: usize) -> Result<Vec<u8>, String> {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        while self.pending.len() < length {
+            if !self.fill(deadline)? {
+                return Err(format!(
+                    "{} timed out waiting for {length} bytes",
+                    self.label
+                ));
+            }
+        }
+        Ok(self.pending.drain(..length).collect())
+    }
+
+    fn expect(&mut self, expected: &[u8]) -> Outcome {
+        let received = self.read_exact(expected.len())?;
+        match received == expected {
+            true => Ok(()),
+            false => Err(format!(
+                "{} expected {} but received {}",
+                self.label,
+                u8s_to_hex_str(expected),
+                u8s_to_hex_str(&received)
+            )),
+        }
+    }
+
+    /// Read a newline-terminated line (without the newline).
+    fn read_line(&mut self) -> Result<String, String> {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        loop {
+            if let Some(position) = self.pending.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=position).collect();
+                return Ok(String::from_utf8_lossy(&line[..position]).to_string());
+            }
+            if !self.fill(deadline)? {
+                return Err(format!("{} timed out waiting for a line", self.label));
+            }
+        }
+    }
+
+    fn expect_line(&mut self, expected: &str) -> Outcome {
+        let line = self.read_line()?;
+        match line == expected {
+            true => Ok(()),
+            false => Err(format!(
+                "{} expected {expected:?} but received {line:?}",
+                self.label
+            )),
+        }
+    }
+
+    /// Expect the server to close the connection (after sending anything still pending).
+    fn expect_disconnect(&mut self) -> Outcome {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        loop {
+            match self.fill(deadline) {
+                Ok(true) => continue,
+                Ok(false) => return Err(format!("{} was not disconnected", self.label)),
+                Err(_) => return Ok(()),
+            }
+        }
+    }
+
+    /// Expect the server not to send anything for a while.
+    fn expect_silence(&mut self) -> Outcome {
+        let deadline = Instant::now() + SILENCE_TIMEOUT;
+        while self.pending.is_empty() {
+            if !self.fill(deadline)? {
+                return Ok(());
+            }
+        }
+        Err(format!(
+            "{} unexpectedly received {}",
+            self.label,
+            u8s_to_hex_str(&self.pending)
+        ))
+    }
+}
+
+struct UdpClient {
+    label: String,
+    socket: UdpSocket,
+    transcript: Rc<RefCell<Transcript>>,
+}
+impl UdpClient {
+    fn send(&self, datagram: &[u8]) {
+        self.transcript
+            .borrow_mut()
+            .record(&self.label, true, datagram);
+        _ = self.socket.send(datagram);
+    }
+
+    fn receive(&self, timeout: Duration) -> Option<Vec<u8>> {
+        _ = self.socket.set_read_timeout(Some(timeout));
+        let mut buffer = [0u8; READ_BUFFER_SIZE];
+        let n = self.socket.recv(&mut buffer).ok()?;
+        self.transcript
+            .borrow_mut()
+            .record(&self.label, false, &buffer[..n]);
+        Some(buffer[..n].to_vec())
+    }
+
+    fn expect(&self, expected: &[u8]) -> Outcome {
+        match self.receive(RESPONSE_TIMEOUT) {
+            Some(datagram) if datagram == expected => Ok(()),
+            Some(datagram) => Err(format!(
+                "{} expected {:?} but received {:?}",
+                self.label,
+                String::from_utf8_lossy(expected),
+                String::from_utf8_lossy(&datagram)
+            )),
+            None => Err(format!("{} timed out waiting for a datagram", self.label)),
+        }
+    }
+
+    fn expect_silence(&self) -> Outcome {
+        match self.receive(SILENCE_TIMEOUT) {
+            Some(datagram) => Err(format!(
+                "{} unexpectedly received {:?}",
+                self.label,
+                String::from_utf8_lossy(&datagram)
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transcript;
+    use crate::trace::{Event, Trace};
+
+    #[test]
+    fn test_transcript_is_replayable() {
+        let mut transcript = Transcript::default();
+        transcript.record("alice", true, b"hi\n");
+        transcript.record("alice", false, b"hi\n");
+        let trace = Trace::parse(&transcript.to_string()).expect("Transcript should be a trace");
+        assert_eq!(
+            vec![
+                Event::Send {
+                    client: "alice".to_string(),
+                    bytes: b"hi\n".to_vec()
+                },
+                Event::Receive {
+                    bytes: b"hi\n".to_vec()
+                },
+            ],
+            trace.events
+        );
+    }
+}
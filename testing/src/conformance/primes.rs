@@ -0,0 +1,153 @@
+use super::{Check, Client, Outcome, Session};
+use serde_json::{json, Value};
+
+pub(super) const CHECKS: &[Check] = &[
+    ("identifies primes", identifies_primes),
+    ("identifies non-primes", identifies_non_primes),
+    ("handles integers too large for a float", large_numbers),
+    ("non-integers are not prime", non_integers),
+    ("negative numbers are not prime", negative_numbers),
+    ("ignores extra fields", extra_fields),
+    ("answers pipelined requests in order", pipelined),
+    ("handles simultaneous clients", simultaneous_clients),
+    ("rejects invalid JSON", invalid_json),
+    ("rejects a missing number", missing_number),
+    ("rejects an unknown method", unknown_method),
+    ("rejects a number as a string", number_as_string),
+];
+
+fn request(number: &str) -> String {
+    format!("{{\"method\":\"isPrime\",\"number\":{number}}}")
+}
+
+fn expect_response(client: &mut Client, prime: bool) -> Outcome {
+    let line = client.read_line()?;
+    let response: Value =
+        serde_json::from_str(&line).map_err(|_| format!("Response {line:?} is not valid JSON"))?;
+    match response == json!({"method": "isPrime", "prime": prime}) {
+        true => Ok(()),
+        false => Err(format!("Expected prime={prime}, but received {line:?}")),
+    }
+}
+
+/// Any response that isn't a well-formed response counts as malformed, but it must be followed
+/// by a disconnect.
+fn expect_malformed(client: &mut Client) -> Outcome {
+    if let Ok(line) = client.read_line() {
+        if let Ok(response) = serde_json::from_str::<Value>(&line) {
+            if response.get("method") == Some(&json!("isPrime"))
+                && response.get("prime").is_some_and(Value::is_boolean)
+            {
+                return Err(format!(
+                    "Malformed request received a valid response {line:?}"
+                ));
+            }
+        }
+    }
+    client.expect_disconnect()
+}
+
+fn identifies_primes(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    for number in ["2", "3", "7", "7919", "2147483647"] {
+        client.send_line(&request(number));
+        expect_response(&mut client, true)?;
+    }
+    Ok(())
+}
+
+fn identifies_non_primes(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    for number in ["0", "1", "4", "7917", "2147483649"] {
+        client.send_line(&request(number));
+        expect_response(&mut client, false)?;
+    }
+    Ok(())
+}
+
+/// Above 2^53 a float can no longer hold every integer, and above 2^64 neither can a u64.
+fn large_numbers(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    let numbers = [
+        ("9007199254740993", false),
+        ("9007199254740997", true),
+        ("18446744073709551617", false),
+        ("18446744073709551629", true),
+        ("170141183460469231731687303715884105727", true),
+        ("170141183460469231731687303715884105729", false),
+    ];
+    for (number, prime) in numbers {
+        client.send_line(&request(number));
+        expect_response(&mut client, prime)?;
+    }
+    Ok(())
+}
+
+fn non_integers(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    for number in ["7.5", "2.000001", "1e-3"] {
+        client.send_line(&request(number));
+        expect_response(&mut client, false)?;
+    }
+    Ok(())
+}
+
+fn negative_numbers(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    for number in ["-7", "-2", "-1"] {
+        client.send_line(&request(number));
+        expect_response(&mut client, false)?;
+    }
+    Ok(())
+}
+
+fn extra_fields(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send_line("{\"number\":13,\"method\":\"isPrime\",\"extra\":[1,2,3]}");
+    expect_response(&mut client, true)
+}
+
+fn pipelined(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send(format!("{}\n{}\n{}\n", request("3"), request("4"), request("5")).as_bytes());
+    expect_response(&mut client, true)?;
+    expect_response(&mut client, false)?;
+    expect_response(&mut client, true)
+}
+
+fn simultaneous_clients(session: &Session) -> Outcome {
+    let mut clients = (0..5)
+        .map(|i| session.connect(&format!("client-{i}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    for client in clients.iter_mut() {
+        client.send_line(&request("11"));
+    }
+    for client in clients.iter_mut().rev() {
+        expect_response(client, true)?;
+    }
+    Ok(())
+}
+
+fn invalid_json(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send_line("{\"method\":\"isPrime\",\"number\":7");
+    expect_malformed(&mut client)
+}
+
+fn missing_number(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send_line("{\"method\":\"isPrime\"}");
+    expect_malformed(&mut client)
+}
+
+fn unknown_method(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send_line("{\"method\":\"isPrim\",\"number\":7}");
+    expect_malformed(&mut client)
+}
+
+fn number_as_string(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send_line("{\"method\":\"isPrime\",\"number\":\"7\"}");
+    expect_malformed(&mut client)
+}
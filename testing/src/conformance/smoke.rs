@@ -0,0 +1,52 @@
+use super::{Check, Outcome, Session};
+
+const LARGE_PAYLOAD_SIZE: usize = 100 * 1_024;
+
+pub(super) const CHECKS: &[Check] = &[
+    ("echoes data", echoes_data),
+    ("echoes binary data", echoes_binary_data),
+    ("echoes a large payload", echoes_large_payload),
+    ("handles simultaneous clients", simultaneous_clients),
+    ("echoes everything before a half-close", half_close),
+];
+
+fn echoes_data(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send(b"hello, world");
+    client.expect(b"hello, world")
+}
+
+fn echoes_binary_data(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    let payload: Vec<u8> = (0..=255).collect();
+    client.send(&payload);
+    client.expect(&payload)
+}
+
+fn echoes_large_payload(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    let payload: Vec<u8> = (0..LARGE_PAYLOAD_SIZE).map(|i| (i % 251) as u8).collect();
+    client.send(&payload);
+    client.expect(&payload)
+}
+
+fn simultaneous_clients(session: &Session) -> Outcome {
+    let mut clients = (0..5)
+        .map(|i| session.connect(&format!("client-{i}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (i, client) in clients.iter_mut().enumerate() {
+        client.send(format!("client {i} says hello").as_bytes());
+    }
+    for (i, client) in clients.iter_mut().enumerate().rev() {
+        client.expect(format!("client {i} says hello").as_bytes())?;
+    }
+    Ok(())
+}
+
+fn half_close(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send(b"goodbye");
+    client.half_close();
+    client.expect(b"goodbye")?;
+    client.expect_disconnect()
+}
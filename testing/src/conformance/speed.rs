@@ -0,0 +1,165 @@
+use super::{Check, Client, Outcome, Session};
+
+const ERROR: u8 = 0x10;
+const HEARTBEAT: u8 = 0x41;
+
+pub(super) const CHECKS: &[Check] = &[
+    ("tickets a speeding car", single_car),
+    ("holds tickets until a dispatcher connects", pending_tickets),
+    ("tickets each road separately", multiple_roads),
+    ("only one ticket per car per day", one_ticket_per_day),
+    ("sends heartbeats", heartbeats),
+    ("zero interval disables heartbeats", zero_heartbeat),
+    ("rejects a second heartbeat request", duplicate_heartbeat),
+    ("rejects a second client declaration", duplicate_declaration),
+    ("rejects plates from non-cameras", plate_from_dispatcher),
+    ("rejects illegal message types", illegal_message),
+];
+
+fn camera(road: u16, mile: u16, limit: u16) -> Vec<u8> {
+    let mut message = vec![0x80];
+    for field in [road, mile, limit] {
+        message.extend_from_slice(&field.to_be_bytes());
+    }
+    message
+}
+
+fn dispatcher(roads: &[u16]) -> Vec<u8> {
+    let mut message = vec![0x81, roads.len() as u8];
+    for road in roads {
+        message.extend_from_slice(&road.to_be_bytes());
+    }
+    message
+}
+
+fn plate(plate: &str, timestamp: u32) -> Vec<u8> {
+    let mut message = vec![0x20, plate.len() as u8];
+    message.extend_from_slice(plate.as_bytes());
+    message.extend_from_slice(&timestamp.to_be_bytes());
+    message
+}
+
+fn want_heartbeat(deciseconds: u32) -> Vec<u8> {
+    let mut message = vec![0x40];
+    message.extend_from_slice(&deciseconds.to_be_bytes());
+    message
+}
+
+fn ticket(plate: &str, road: u16, first: (u16, u32), second: (u16, u32), speed: u16) -> Vec<u8> {
+    let mut message = vec![0x21, plate.len() as u8];
+    message.extend_from_slice(plate.as_bytes());
+    message.extend_from_slice(&road.to_be_bytes());
+    for (mile, timestamp) in [first, second] {
+        message.extend_from_slice(&mile.to_be_bytes());
+        message.extend_from_slice(&timestamp.to_be_bytes());
+    }
+    message.extend_from_slice(&speed.to_be_bytes());
+    message
+}
+
+/// An error message (any text) followed by a disconnect.
+fn expect_error(client: &mut Client) -> Outcome {
+    client.expect(&[ERROR])?;
+    let length = client.read_exact(1)?[0];
+    client.read_exact(length as usize)?;
+    client.expect_disconnect()
+}
+
+fn single_car(session: &Session) -> Outcome {
+    let mut first = session.connect("camera-one")?;
+    let mut second = session.connect("camera-two")?;
+    let mut dispatch = session.connect("dispatcher")?;
+    first.send(&camera(123, 8, 60));
+    second.send(&camera(123, 9, 60));
+    dispatch.send(&dispatcher(&[123]));
+    first.send(&plate("UN1X", 0));
+    second.send(&plate("UN1X", 45));
+    dispatch.expect(&ticket("UN1X", 123, (8, 0), (9, 45), 8000))
+}
+
+fn pending_tickets(session: &Session) -> Outcome {
+    let mut first = session.connect("camera-one")?;
+    let mut second = session.connect("camera-two")?;
+    first.send(&camera(124, 0, 50));
+    second.send(&camera(124, 10, 50));
+    first.send(&plate("LATE01", 1_000));
+    second.send(&plate("LATE01", 1_360));
+    let mut dispatch = session.connect("dispatcher")?;
+    dispatch.send(&dispatcher(&[124]));
+    dispatch.expect(&ticket("LATE01", 124, (0, 1_000), (10, 1_360), 10_000))
+}
+
+fn multiple_roads(session: &Session) -> Outcome {
+    let mut road_one = session.connect("dispatcher-one")?;
+    let mut road_two = session.connect("dispatcher-two")?;
+    road_one.send(&dispatcher(&[201]));
+    road_two.send(&dispatcher(&[202]));
+    let mut cameras = vec![];
+    for (road, mile) in [(201, 0), (201, 5), (202, 0), (202, 5)] {
+        let mut client = session.connect(&format!("camera-{road}-{mile}"))?;
+        client.send(&camera(road, mile, 30));
+        cameras.push(client);
+    }
+    // Both cars do 5 miles in 5 minutes: 60mph.
+    cameras[0].send(&plate("ROAD1", 0));
+    cameras[1].send(&plate("ROAD1", 300));
+    cameras[2].send(&plate("ROAD2", 0));
+    cameras[3].send(&plate("ROAD2", 300));
+    road_one.expect(&ticket("ROAD1", 201, (0, 0), (5, 300), 6_000))?;
+    road_two.expect(&ticket("ROAD2", 202, (0, 0), (5, 300), 6_000))
+}
+
+fn one_ticket_per_day(session: &Session) -> Outcome {
+    let mut dispatch = session.connect("dispatcher")?;
+    dispatch.send(&dispatcher(&[300]));
+    let mut first = session.connect("camera-one")?;
+    let mut second = session.connect("camera-two")?;
+    first.send(&camera(300, 0, 60));
+    second.send(&camera(300, 10, 60));
+    // Ten miles in 5 minutes (120mph), twice on the same day.
+    first.send(&plate("REPEAT", 1_000));
+    second.send(&plate("REPEAT", 1_300));
+    dispatch.expect(&ticket("REPEAT", 300, (0, 1_000), (10, 1_300), 12_000))?;
+    first.send(&plate("REPEAT", 2_000));
+    second.send(&plate("REPEAT", 2_300));
+    dispatch.expect_silence()
+}
+
+fn heartbeats(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send(&want_heartbeat(1));
+    client.expect(&[HEARTBEAT, HEARTBEAT, HEARTBEAT])
+}
+
+fn zero_heartbeat(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send(&want_heartbeat(0));
+    client.expect_silence()
+}
+
+fn duplicate_heartbeat(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send(&want_heartbeat(0));
+    client.send(&want_heartbeat(0));
+    expect_error(&mut client)
+}
+
+fn duplicate_declaration(session: &Session) -> Outcome {
+    let mut client = session.connect("camera")?;
+    client.send(&camera(400, 0, 60));
+    client.send(&dispatcher(&[400]));
+    expect_error(&mut client)
+}
+
+fn plate_from_dispatcher(session: &Session) -> Outcome {
+    let mut client = session.connect("dispatcher")?;
+    client.send(&dispatcher(&[500]));
+    client.send(&plate("WRONG", 0));
+    expect_error(&mut client)
+}
+
+fn illegal_message(session: &Session) -> Outcome {
+    let mut client = session.connect("client")?;
+    client.send(&[ERROR, 0x00]);
+    expect_error(&mut client)
+}
@@ -0,0 +1,96 @@
+//! Send payloads the way a real network might deliver them: split at arbitrary boundaries,
+//! several frames at once, with delays in between, or followed by a half-close.
+
+use crate::rng::{Rng, SEED_VARIABLE};
+use std::io::Write;
+use std::net::{Shutdown, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+// Same as `send_bytes_from!`: give the server a chance to process what was sent.
+const SETTLE_DELAY: Duration = Duration::from_millis(5);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fragment {
+    /// Write the whole payload at once.
+    Whole,
+    /// Write one byte at a time.
+    ByteByByte,
+    /// Write chunks of random lengths (between 1 and the remaining payload length).
+    Random,
+}
+
+pub struct FaultInjector {
+    fragment: Fragment,
+    jitter: Duration,
+    seed: u64,
+    rng: Rng,
+}
+impl FaultInjector {
+    /// The random seed is taken from the `FAULT_SEED` environment variable if set, otherwise from
+    /// the current time. It is printed so that a failing test can be reproduced.
+    pub fn new(fragment: Fragment) -> Self {
+        Self::new_with_seed(fragment, Rng::seed_from_env())
+    }
+
+    pub fn new_with_seed(fragment: Fragment, seed: u64) -> Self {
+        println!("Fault injection: {fragment:?} fragments using seed {SEED_VARIABLE}={seed}.");
+        Self {
+            fragment,
+            jitter: Duration::ZERO,
+            seed,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Sleep for a random duration, up to `jitter`, before writing each fragment.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn send(&mut self, stream: &mut TcpStream, payload: &[u8]) {
+        let mut remaining = payload;
+        while !remaining.is_empty() {
+            let length = match self.fragment {
+                Fragment::Whole => remaining.len(),
+                Fragment::ByteByByte => 1,
+                Fragment::Random => 1 + self.rng.next_below(remaining.len() as u64) as usize,
+            };
+            if !self.jitter.is_zero() {
+                let nanos = self.rng.next_below(self.jitter.as_nanos() as u64 + 1);
+                thread::sleep(Duration::from_nanos(nanos));
+            }
+            let (fragment, rest) = remaining.split_at(length);
+            _ = stream.write_all(fragment);
+            _ = stream.flush();
+            remaining = rest;
+        }
+        thread::sleep(SETTLE_DELAY);
+    }
+
+    /// Send several frames as if they had arrived in a single packet (before fragmenting).
+    pub fn send_coalesced(&mut self, stream: &mut TcpStream, frames: &[&[u8]]) {
+        self.send(stream, &frames.concat());
+    }
+
+    /// Close the writing half of the connection, while still being able to read responses.
+    pub fn half_close(&mut self, stream: &mut TcpStream) {
+        _ = stream.shutdown(Shutdown::Write);
+        thread::sleep(SETTLE_DELAY);
+    }
+}
+
+#[macro_export]
+macro_rules! send_bytes_with_faults {
+    ($f:expr, $s:expr, $h:expr) => {{
+        $f.send(
+            &mut $s,
+            &$crate::hex_str_to_u8s($h).expect("Invalid hex code provided for integration test."),
+        );
+    }};
+}
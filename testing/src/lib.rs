@@ -1,3 +1,13 @@
+pub mod clock;
+pub mod conformance;
+pub mod fault;
+pub mod load;
+mod rng;
+pub mod snapshot;
+pub mod trace;
+pub mod udp;
+pub mod upstream;
+
 use std::net::{TcpListener, TcpStream};
 
 pub fn listen_on_available_port() -> (TcpListener, u16) {
@@ -7,6 +17,11 @@ pub fn listen_on_available_port() -> (TcpListener, u16) {
     let Ok(addr) = listener.local_addr() else {
         panic!("Could not determine OS-assigned port.");
     };
+    // Applications poll the listener in the same loop as they process messages; match the
+    // behaviour of `common::get_tcp_listener` so that loop doesn't block waiting to accept.
+    listener
+        .set_nonblocking(true)
+        .expect("Could not set TCP listener as non-blocking.");
     println!(
         "Integration test: running application on port {}.",
         addr.port()
@@ -32,6 +47,7 @@ pub fn u8s_to_hex_str(bytes: &[u8]) -> String {
         .join(" ")
 }
 
+#[allow(clippy::result_unit_err)]
 pub fn hex_str_to_u8s(hex: &str) -> Result<Vec<u8>, ()> {
     let stripped = hex
         .chars()
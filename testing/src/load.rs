@@ -0,0 +1,370 @@
+//! Generate load against a running server, measuring throughput and request latency.
+
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const ECHO_PAYLOAD_SIZE: usize = 1_024;
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    /// Smoke Test: write a payload, wait for all of it to be echoed back.
+    Echo,
+    /// Prime Time: one `isPrime` request per response.
+    Primes,
+    /// Means to an End: insert a price, then query the mean of every price so far.
+    Keystore,
+    /// Speed Daemon: two cameras report a speeding car, and the road's dispatcher waits for the ticket.
+    Speed,
+}
+impl FromStr for Profile {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "echo" => Ok(Self::Echo),
+            "primes" => Ok(Self::Primes),
+            "keystore" => Ok(Self::Keystore),
+            "speed" => Ok(Self::Speed),
+            _ => Err(format!(
+                "Unknown profile \"{s}\" (echo, primes, keystore, speed)."
+            )),
+        }
+    }
+}
+
+pub struct LoadConfig {
+    pub profile: Profile,
+    pub address: String,
+    pub connections: usize,
+    pub duration: Duration,
+    /// Print running totals every second (useful for long soak tests).
+    pub progress: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub elapsed: Duration,
+    pub errors: u64,
+    /// Latency of every successful request, sorted ascending.
+    pub latencies: Vec<Duration>,
+}
+impl Report {
+    pub fn throughput(&self) -> f64 {
+        match self.elapsed.is_zero() {
+            true => 0.0,
+            false => self.latencies.len() as f64 / self.elapsed.as_secs_f64(),
+        }
+    }
+
+    /// Nearest-rank percentile (0.0 to 100.0) of request latency.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let rank = ((percentile / 100.0) * self.latencies.len() as f64).ceil() as usize;
+        Some(self.latencies[rank.clamp(1, self.latencies.len()) - 1])
+    }
+}
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} requests ({} errors) in {:.2?}: {:.1} requests/second",
+            self.latencies.len(),
+            self.errors,
+            self.elapsed,
+            self.throughput()
+        )?;
+        for percentile in [50.0, 90.0, 99.0, 100.0] {
+            if let Some(latency) = self.percentile(percentile) {
+                writeln!(f, "  p{percentile}: {latency:.2?}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+trait Session {
+    /// Perform one request, returning how long the server took to respond.
+    fn request(&mut self) -> io::Result<Duration>;
+}
+
+pub fn run(config: &LoadConfig) -> Report {
+    let deadline = Instant::now() + config.duration;
+    let completed = Arc::new(AtomicU64::new(0));
+    let errors = Arc::new(AtomicU64::new(0));
+    let latencies: Arc<Mutex<Vec<Duration>>> = Arc::default();
+
+    let start = Instant::now();
+    let workers = (0..config.connections)
+        .map(|worker| {
+            let (address, profile) = (config.address.clone(), config.profile);
+            let (completed, errors, latencies) =
+                (completed.clone(), errors.clone(), latencies.clone());
+            thread::spawn(move || {
+                let mut session = match connect(profile, &address, worker) {
+                    Ok(session) => session,
+                    Err(_) => {
+                        errors.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                };
+                let mut measured: Vec<Duration> = vec![];
+                while Instant::now() < deadline {
+                    match session.request() {
+                        Ok(latency) => {
+                            measured.push(latency);
+                            completed.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(_) => {
+                            // The connection is in an unknown state, so give up on it.
+                            errors.fetch_add(1, Ordering::Relaxed);
+                            break;
+                        }
+                    }
+                }
+                latencies
+                    .lock()
+                    .expect("Latencies poisoned.")
+                    .extend(measured);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if config.progress {
+        while Instant::now() < deadline {
+            thread::sleep(
+                PROGRESS_INTERVAL.min(deadline.saturating_duration_since(Instant::now())),
+            );
+            println!(
+                "{:>6.1?}: {} requests, {} errors",
+                start.elapsed(),
+                completed.load(Ordering::Relaxed),
+                errors.load(Ordering::Relaxed)
+            );
+        }
+    }
+    for worker in workers {
+        _ = worker.join();
+    }
+
+    let mut latencies = std::mem::take(&mut *latencies.lock().expect("Latencies poisoned."));
+    latencies.sort();
+    Report {
+        elapsed: start.elapsed(),
+        errors: errors.load(Ordering::Relaxed),
+        latencies,
+    }
+}
+
+fn open(address: &str) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+fn connect(profile: Profile, address: &str, worker: usize) -> io::Result<Box<dyn Session>> {
+    Ok(match profile {
+        Profile::Echo => Box::new(EchoSession {
+            stream: open(address)?,
+            payload: (0..ECHO_PAYLOAD_SIZE).map(|i| i as u8).collect(),
+        }),
+        Profile::Primes => Box::new(PrimesSession {
+            stream: open(address)?,
+            number: worker as u64,
+            pending: vec![],
+        }),
+        Profile::Keystore => Box::new(KeystoreSession {
+            stream: open(address)?,
+            timestamp: 0,
+        }),
+        Profile::Speed => Box::new(SpeedSession::new(address, worker as u16)?),
+    })
+}
+
+fn read_response(stream: &mut TcpStream, length: usize) -> io::Result<Vec<u8>> {
+    let mut response = vec![0u8; length];
+    stream.read_exact(&mut response)?;
+    Ok(response)
+}
+
+struct EchoSession {
+    stream: TcpStream,
+    payload: Vec<u8>,
+}
+impl Session for EchoSession {
+    fn request(&mut self) -> io::Result<Duration> {
+        let now = Instant::now();
+        self.stream.write_all(&self.payload)?;
+        let response = read_response(&mut self.stream, self.payload.len())?;
+        if response != self.payload {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Echo mismatch."));
+        }
+        Ok(now.elapsed())
+    }
+}
+
+struct PrimesSession {
+    stream: TcpStream,
+    number: u64,
+    pending: Vec<u8>,
+}
+impl Session for PrimesSession {
+    fn request(&mut self) -> io::Result<Duration> {
+        self.number += 1;
+        let request = format!("{{\"method\":\"isPrime\",\"number\":{}}}\n", self.number);
+        let now = Instant::now();
+        self.stream.write_all(request.as_bytes())?;
+        // Each response is a flat JSON object, so it is complete at its closing brace.
+        let mut buffer = [0u8; 256];
+        while !self.pending.contains(&b'}') {
+            match self.stream.read(&mut buffer)? {
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                n => self.pending.extend_from_slice(&buffer[..n]),
+            }
+        }
+        let latency = now.elapsed();
+        if let Some(end) = self.pending.iter().position(|&byte| byte == b'}') {
+            self.pending.drain(..=end);
+        }
+        Ok(latency)
+    }
+}
+
+struct KeystoreSession {
+    stream: TcpStream,
+    timestamp: i32,
+}
+impl Session for KeystoreSession {
+    fn request(&mut self) -> io::Result<Duration> {
+        self.timestamp += 1;
+        let mut messages: Vec<u8> = vec![b'I'];
+        messages.extend_from_slice(&self.timestamp.to_be_bytes());
+        messages.extend_from_slice(&100i32.to_be_bytes());
+        messages.push(b'Q');
+        messages.extend_from_slice(&0i32.to_be_bytes());
+        messages.extend_from_slice(&self.timestamp.to_be_bytes());
+        let now = Instant::now();
+        self.stream.write_all(&messages)?;
+        read_response(&mut self.stream, 4)?;
+        Ok(now.elapsed())
+    }
+}
+
+struct SpeedSession {
+    first_camera: TcpStream,
+    second_camera: TcpStream,
+    dispatcher: TcpStream,
+    road: u16,
+    car: u32,
+}
+impl SpeedSession {
+    // Cameras ten miles apart on this worker's own road, so tickets only go to its dispatcher.
+    fn new(address: &str, road: u16) -> io::Result<Self> {
+        let mut first_camera = open(address)?;
+        let mut second_camera = open(address)?;
+        let mut dispatcher = open(address)?;
+        first_camera.write_all(&Self::camera(road, 0))?;
+        second_camera.write_all(&Self::camera(road, 10))?;
+        let mut declaration = vec![0x81, 0x01];
+        declaration.extend_from_slice(&road.to_be_bytes());
+        dispatcher.write_all(&declaration)?;
+        Ok(Self {
+            first_camera,
+            second_camera,
+            dispatcher,
+            road,
+            car: 0,
+        })
+    }
+
+    fn camera(road: u16, mile: u16) -> Vec<u8> {
+        let mut declaration = vec![0x80];
+        declaration.extend_from_slice(&road.to_be_bytes());
+        declaration.extend_from_slice(&mile.to_be_bytes());
+        declaration.extend_from_slice(&60u16.to_be_bytes());
+        declaration
+    }
+
+    fn plate(plate: &[u8], timestamp: u32) -> Vec<u8> {
+        let mut message = vec![0x20, plate.len() as u8];
+        message.extend_from_slice(plate);
+        message.extend_from_slice(&timestamp.to_be_bytes());
+        message
+    }
+}
+impl Session for SpeedSession {
+    fn request(&mut self) -> io::Result<Duration> {
+        // A new plate each time (and per worker), as each car only gets one ticket per day.
+        self.car += 1;
+        let plate = format!("R{:04X}C{:06X}", self.road, self.car).into_bytes();
+        let timestamp = self.car * 60;
+        self.first_camera
+            .write_all(&Self::plate(&plate, timestamp))?;
+        let now = Instant::now();
+        // Ten miles in one minute.
+        self.second_camera
+            .write_all(&Self::plate(&plate, timestamp + 60))?;
+        // Ticket: type, plate (length-prefixed), then road, two mile/timestamp pairs and speed.
+        let header = read_response(&mut self.dispatcher, 2)?;
+        read_response(&mut self.dispatcher, header[1] as usize + 16)?;
+        Ok(now.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, LoadConfig, Profile, Report};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_percentiles() {
+        let report = Report {
+            elapsed: Duration::from_secs(2),
+            errors: 0,
+            latencies: (1..=100).map(Duration::from_millis).collect(),
+        };
+        assert_eq!(50.0, report.throughput());
+        assert_eq!(Some(Duration::from_millis(50)), report.percentile(50.0));
+        assert_eq!(Some(Duration::from_millis(99)), report.percentile(99.0));
+        assert_eq!(Some(Duration::from_millis(100)), report.percentile(100.0));
+        assert_eq!(None, Report::default().percentile(50.0));
+    }
+
+    #[test]
+    fn test_echo_profile() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                thread::spawn(move || {
+                    let mut buffer = [0u8; 1_024];
+                    while let Ok(n @ 1..) = stream.read(&mut buffer) {
+                        _ = stream.write_all(&buffer[..n]);
+                    }
+                });
+            }
+        });
+
+        let report = run(&LoadConfig {
+            profile: Profile::Echo,
+            address,
+            connections: 2,
+            duration: Duration::from_millis(100),
+            progress: false,
+        });
+        assert_eq!(0, report.errors);
+        assert!(!report.latencies.is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub(crate) const SEED_VARIABLE: &str = "FAULT_SEED";
+
+/// Xorshift64*: plenty random enough to simulate network faults, and reproducible from a seed.
+pub(crate) struct Rng {
+    state: u64,
+}
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // Xorshift gets stuck on zero.
+        Self { state: seed.max(1) }
+    }
+
+    /// The seed is taken from the `FAULT_SEED` environment variable if set, otherwise from the
+    /// current time.
+    pub(crate) fn seed_from_env() -> u64 {
+        match env::var(SEED_VARIABLE)
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+        {
+            Some(seed) => seed,
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_nanos() as u64)
+                .unwrap_or_default(),
+        }
+    }
+
+    pub(crate) fn next_below(&mut self, bound: u64) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) % bound.max(1)
+    }
+
+    /// True with the given probability (between 0.0 and 1.0).
+    pub(crate) fn chance(&mut self, probability: f64) -> bool {
+        (self.next_below(1_000_000) as f64) < probability * 1_000_000.0
+    }
+
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.next_below(i as u64 + 1) as usize);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn test_seed_is_reproducible() {
+        let mut first = Rng::new(42);
+        let mut second = Rng::new(42);
+        for _ in 0..100 {
+            assert_eq!(first.next_below(10), second.next_below(10));
+        }
+    }
+
+    #[test]
+    fn test_within_bound() {
+        let mut rng = Rng::new(0);
+        assert!((0..1_000).all(|_| rng.next_below(7) < 7));
+    }
+
+    #[test]
+    fn test_shuffle_keeps_items() {
+        let mut items = [1, 2, 3, 4, 5, 6, 7, 8];
+        Rng::new(7).shuffle(&mut items);
+        items.sort();
+        assert_eq!([1, 2, 3, 4, 5, 6, 7, 8], items);
+    }
+}
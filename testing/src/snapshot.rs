@@ -0,0 +1,248 @@
+//! Golden transcripts: record a whole server conversation (every client, in order) and compare it
+//! against a snapshot file, so that changes in behaviour show up as a reviewable diff.
+//!
+//! The first run writes the snapshot. Later runs compare against it, unless `UPDATE_SNAPSHOTS=1`
+//! is set, in which case the snapshot is overwritten with what the server now does.
+
+use crate::{connect, u8s_to_hex_str};
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const UPDATE_VARIABLE: &str = "UPDATE_SNAPSHOTS";
+// The conversation has settled once no client has received anything for this long.
+const QUIET_PERIOD: Duration = Duration::from_millis(50);
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(2);
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// One line per line of text, with control characters escaped (for line-based protocols).
+    Text,
+    /// Space-separated hex bytes (for binary protocols).
+    Hex,
+}
+
+struct Client {
+    name: String,
+    stream: Option<TcpStream>,
+    received: Vec<u8>,
+    hung_up: bool,
+}
+
+pub struct Conversation {
+    port: u16,
+    format: Format,
+    clients: Vec<Client>,
+    lines: Vec<String>,
+}
+impl Conversation {
+    pub fn new(port: u16, format: Format) -> Self {
+        Self {
+            port,
+            format,
+            clients: vec![],
+            lines: vec![],
+        }
+    }
+
+    /// Connect a new named client, recording anything the server greets it with.
+    pub fn connect(&mut self, name: &str) {
+        self.clients.push(Client {
+            name: name.to_string(),
+            stream: Some(connect(self.port)),
+            received: vec![],
+            hung_up: false,
+        });
+        self.lines.push(format!("{name} connected"));
+        self.settle();
+    }
+
+    /// Send bytes from a client (connecting it first if necessary), then record every response.
+    pub fn send(&mut self, name: &str, bytes: &[u8]) {
+        if !self.clients.iter().any(|client| client.name == name) {
+            self.connect(name);
+        }
+        let client = self.client(name);
+        if let Some(stream) = client.stream.as_mut() {
+            _ = stream.write_all(bytes);
+        }
+        let line = self.format_bytes(bytes);
+        self.lines
+            .extend(line.into_iter().map(|line| format!("{name} -> {line}")));
+        self.settle();
+    }
+
+    /// Close a client's connection, then record how everyone else is told about it.
+    pub fn disconnect(&mut self, name: &str) {
+        if let Some(stream) = self.client(name).stream.take() {
+            _ = stream.shutdown(Shutdown::Both);
+        }
+        self.lines.push(format!("{name} disconnected"));
+        self.settle();
+    }
+
+    pub fn transcript(&self) -> String {
+        self.lines.iter().map(|line| format!("{line}\n")).collect()
+    }
+
+    /// Compare the transcript against the snapshot at `path`, writing it if it does not exist yet
+    /// (or `UPDATE_SNAPSHOTS` is set). Panics with a line diff when they differ.
+    pub fn assert_snapshot(&self, path: &Path) {
+        let actual = self.transcript();
+        let update = std::env::var(UPDATE_VARIABLE).is_ok_and(|value| !matches!(&*value, "" | "0"));
+        match fs::read_to_string(path) {
+            Ok(expected) if !update => assert!(
+                expected == actual,
+                "Snapshot {} does not match (run with {UPDATE_VARIABLE}=1 to accept):\n{}",
+                path.display(),
+                diff(&expected, &actual)
+            ),
+            _ => {
+                if let Some(directory) = path.parent() {
+                    fs::create_dir_all(directory).expect("Could not create snapshot directory.");
+                }
+                fs::write(path, actual).expect("Could not write snapshot.");
+                println!("Wrote snapshot {}.", path.display());
+            }
+        }
+    }
+
+    fn client(&mut self, name: &str) -> &mut Client {
+        self.clients
+            .iter_mut()
+            .find(|client| client.name == name)
+            .expect("Client has not connected.")
+    }
+
+    fn format_bytes(&self, bytes: &[u8]) -> Vec<String> {
+        match self.format {
+            Format::Hex => vec![u8s_to_hex_str(bytes)],
+            Format::Text => bytes
+                .split_inclusive(|&byte| byte == b'\n')
+                .map(|line| String::from_utf8_lossy(line).escape_debug().to_string())
+                .collect(),
+        }
+    }
+
+    /// Wait until the server has stopped talking, then record what each client received (in the
+    /// order they connected, as arrival order between clients is not deterministic).
+    fn settle(&mut self) {
+        let start = Instant::now();
+        let mut last_received = Instant::now();
+        let mut buffer = [0u8; 1_024];
+        while last_received.elapsed() < QUIET_PERIOD && start.elapsed() < SETTLE_TIMEOUT {
+            for client in self.clients.iter_mut() {
+                let Some(stream) = client.stream.as_mut() else {
+                    continue;
+                };
+                loop {
+                    match stream.read(&mut buffer) {
+                        Ok(0) => {
+                            client.stream = None;
+                            client.hung_up = true;
+                            break;
+                        }
+                        Ok(n) => {
+                            client.received.extend_from_slice(&buffer[..n]);
+                            last_received = Instant::now();
+                        }
+                        Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                        Err(_) => {
+                            client.stream = None;
+                            break;
+                        }
+                    }
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        for index in 0..self.clients.len() {
+            let received = std::mem::take(&mut self.clients[index].received);
+            let name = self.clients[index].name.clone();
+            if !received.is_empty() {
+                let lines = self.format_bytes(&received);
+                self.lines
+                    .extend(lines.into_iter().map(|line| format!("{name} <- {line}")));
+            }
+            if std::mem::take(&mut self.clients[index].hung_up) {
+                self.lines
+                    .push(format!("{name} was disconnected by the server"));
+            }
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! assert_snapshot {
+    ($c:expr, $n:literal) => {{
+        $c.assert_snapshot(
+            &::std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests")
+                .join("snapshots")
+                .join(concat!($n, ".snap")),
+        );
+    }};
+}
+
+/// Line diff (longest common subsequence) with unchanged lines prefixed by a space, removed lines
+/// by `-` and added lines by `+`.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    // common[i][j] is the length of the longest common subsequence of expected[i..] and actual[j..].
+    let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = match expected[i] == actual[j] {
+                true => common[i + 1][j + 1] + 1,
+                false => common[i + 1][j].max(common[i][j + 1]),
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut output = String::new();
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            output.push_str(&format!("  {}\n", expected[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if i < expected.len() && (j == actual.len() || common[i + 1][j] >= common[i][j + 1])
+        {
+            output.push_str(&format!("- {}\n", expected[i]));
+            i += 1;
+        } else {
+            output.push_str(&format!("+ {}\n", actual[j]));
+            j += 1;
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Conversation, Format};
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(
+            "  a\n- b\n+ c\n  d\n+ e\n",
+            diff("a\nb\nd\n", "a\nc\nd\ne\n")
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let text = Conversation::new(0, Format::Text);
+        assert_eq!(
+            vec!["hi\\n", "there\\t\\n", "partial"],
+            text.format_bytes(b"hi\nthere\t\npartial")
+        );
+        let hex = Conversation::new(0, Format::Hex);
+        assert_eq!(vec!["41 0a"], hex.format_bytes(b"A\n"));
+    }
+}
@@ -0,0 +1,285 @@
+//! Replay annotated session traces (in the format of `speed/speed.log`) against a running server.
+//!
+//! Each non-empty line of a trace is one of:
+//! - `(label) <uuid>: <hex bytes> <annotation>`: bytes sent by the named client.
+//! - `<whitespace> <hex bytes> <annotation>`: more bytes sent by the previously named client.
+//! - `>>> <hex bytes> <annotation>`: bytes the server sent (to any client).
+//! - `[...] ...`: notes from the official checker, which are ignored.
+//!
+//! Annotations (anything after the hex bytes) are ignored.
+
+use crate::{connect, u8s_to_hex_str};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Give the server a chance to process each message, in the same way `send_bytes_from!` does.
+const SEND_DELAY: Duration = Duration::from_millis(5);
+const SERVER_OUTPUT_PREFIX: &str = ">>>";
+
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    Send { client: String, bytes: Vec<u8> },
+    Receive { bytes: Vec<u8> },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub reason: &'static str,
+}
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub events: Vec<Event>,
+}
+impl Trace {
+    pub fn parse(log: &str) -> Result<Self, ParseError> {
+        let mut events: Vec<Event> = vec![];
+        let mut current_client: Option<String> = None;
+
+        for (index, line) in log.lines().enumerate() {
+            let error = |reason| ParseError {
+                line: index + 1,
+                reason,
+            };
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('[') {
+                continue;
+            }
+
+            if let Some(output) = trimmed.strip_prefix(SERVER_OUTPUT_PREFIX) {
+                let bytes =
+                    leading_hex(output).ok_or_else(|| error("server output has no bytes"))?;
+                events.push(Event::Receive { bytes });
+            } else if let Some(labelled) = trimmed.strip_prefix('(') {
+                let (label, rest) = labelled
+                    .split_once(')')
+                    .ok_or_else(|| error("unterminated client label"))?;
+                let (_uuid, payload) = rest
+                    .split_once(':')
+                    .ok_or_else(|| error("missing connection identifier"))?;
+                let bytes =
+                    leading_hex(payload).ok_or_else(|| error("client input has no bytes"))?;
+                current_client = Some(label.trim().to_string());
+                events.push(Event::Send {
+                    client: label.trim().to_string(),
+                    bytes,
+                });
+            } else if line.starts_with(char::is_whitespace) {
+                let client = current_client
+                    .clone()
+                    .ok_or_else(|| error("continuation line without a preceding client"))?;
+                let bytes =
+                    leading_hex(trimmed).ok_or_else(|| error("continuation has no bytes"))?;
+                events.push(Event::Send { client, bytes });
+            } else {
+                return Err(error("unrecognised line"));
+            }
+        }
+
+        Ok(Self { events })
+    }
+
+    /// Client labels, in the order they first send something.
+    pub fn clients(&self) -> Vec<&str> {
+        let mut clients: Vec<&str> = vec![];
+        for event in &self.events {
+            if let Event::Send { client, .. } = event {
+                if !clients.contains(&client.as_str()) {
+                    clients.push(client);
+                }
+            }
+        }
+        clients
+    }
+
+    /// Connect each labelled client to the server on `port` (when it first appears in the trace),
+    /// send its bytes in trace order, then wait up to `timeout` for the server to send everything
+    /// the trace recorded. Any received message exactly matching one of `ignore` (such as
+    /// timing-dependent heartbeats) is discarded from both sides of the comparison.
+    pub fn replay(&self, port: u16, ignore: &[&[u8]], timeout: Duration) -> Diff {
+        let mut streams: HashMap<&str, TcpStream> = HashMap::new();
+        let mut received: HashMap<&str, Vec<u8>> = HashMap::new();
+        let mut expected: Vec<Vec<u8>> = vec![];
+
+        for event in &self.events {
+            match event {
+                Event::Send { client, bytes } => {
+                    let stream = streams
+                        .entry(client.as_str())
+                        .or_insert_with(|| connect(port));
+                    _ = stream.write_all(bytes);
+                    thread::sleep(SEND_DELAY);
+                    collect(&mut streams, &mut received);
+                }
+                Event::Receive { bytes } => {
+                    if !ignore.contains(&bytes.as_slice()) {
+                        expected.push(bytes.to_owned());
+                    }
+                }
+            }
+        }
+
+        let now = Instant::now();
+        loop {
+            collect(&mut streams, &mut received);
+            let diff = Diff::compare(&expected, &received, ignore);
+            if diff.missing.is_empty() || now.elapsed() > timeout {
+                return diff;
+            }
+            thread::sleep(SEND_DELAY);
+        }
+    }
+}
+
+fn leading_hex(input: &str) -> Option<Vec<u8>> {
+    let bytes = input
+        .split_whitespace()
+        .take_while(|token| token.len() == 2 && token.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|token| u8::from_str_radix(token, 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .ok()?;
+    match bytes.is_empty() {
+        true => None,
+        false => Some(bytes),
+    }
+}
+
+fn collect<'a>(
+    streams: &mut HashMap<&'a str, TcpStream>,
+    received: &mut HashMap<&'a str, Vec<u8>>,
+) {
+    let mut buffer = [0u8; 512];
+    for (client, stream) in streams.iter_mut() {
+        loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => received
+                    .entry(client)
+                    .or_default()
+                    .extend_from_slice(&buffer[..n]),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/// Difference between what a trace expected the server to send, and what it actually sent.
+#[derive(Debug, Default, PartialEq)]
+pub struct Diff {
+    /// Server messages from the trace that no client received.
+    pub missing: Vec<Vec<u8>>,
+    /// Bytes received by each client that did not match any server message in the trace.
+    pub unexpected: Vec<(String, Vec<u8>)>,
+}
+impl Diff {
+    /// Server output in a trace is not attributed to a client, so match each expected message
+    /// against the front of whichever client's stream it appears at.
+    fn compare(expected: &[Vec<u8>], received: &HashMap<&str, Vec<u8>>, ignore: &[&[u8]]) -> Self {
+        let mut pending: Vec<&[u8]> = expected.iter().map(Vec::as_slice).collect();
+        let mut remaining: Vec<(&str, &[u8])> = received
+            .iter()
+            .map(|(client, bytes)| (*client, bytes.as_slice()))
+            .collect();
+        remaining.sort();
+
+        let mut progress = true;
+        while progress {
+            progress = false;
+            for (_, stream) in remaining.iter_mut() {
+                while let Some(skip) = ignore.iter().find(|ignored| stream.starts_with(ignored)) {
+                    *stream = &stream[skip.len()..];
+                }
+                if let Some(position) = pending
+                    .iter()
+                    .position(|message| stream.starts_with(message))
+                {
+                    *stream = &stream[pending.remove(position).len()..];
+                    progress = true;
+                }
+            }
+        }
+
+        Self {
+            missing: pending.into_iter().map(<[u8]>::to_vec).collect(),
+            unexpected: remaining
+                .into_iter()
+                .filter(|(_, bytes)| !bytes.is_empty())
+                .map(|(client, bytes)| (client.to_string(), bytes.to_vec()))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+impl Display for Diff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for bytes in &self.missing {
+            writeln!(f, "- {}", u8s_to_hex_str(bytes))?;
+        }
+        for (client, bytes) in &self.unexpected {
+            writeln!(f, "+ ({client}) {}", u8s_to_hex_str(bytes))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, ParseError, Trace};
+
+    #[test]
+    fn test_parse() {
+        let trace = Trace::parse(
+            "(camera-one)   9e8fab30-0aa8-4998-ba65-ecf7c7167eeb: 80 28 f1 00 0a 00 3c   \"<IAmCamera{}>\"\n\
+             (dispatcher)   c271376c-af2a-433c-8933-65b5f02e422a: 81 01                  incomplete\n\
+             \x20                                                     28 f1                  \"<IAmDispatcher{}>\"\n\
+             >>> 41                                                                         \"<Heartbeat{}>\"\n\
+             \n\
+             [4multiroads.test] NOTE:check starts\n",
+        )
+        .expect("Valid trace");
+        assert_eq!(
+            vec![
+                Event::Send {
+                    client: "camera-one".to_string(),
+                    bytes: vec![0x80, 0x28, 0xf1, 0x00, 0x0a, 0x00, 0x3c]
+                },
+                Event::Send {
+                    client: "dispatcher".to_string(),
+                    bytes: vec![0x81, 0x01]
+                },
+                Event::Send {
+                    client: "dispatcher".to_string(),
+                    bytes: vec![0x28, 0xf1]
+                },
+                Event::Receive { bytes: vec![0x41] },
+            ],
+            trace.events
+        );
+        assert_eq!(vec!["camera-one", "dispatcher"], trace.clients());
+    }
+
+    #[test]
+    fn test_parse_orphan_continuation() {
+        assert_eq!(
+            ParseError {
+                line: 1,
+                reason: "continuation line without a preceding client"
+            },
+            Trace::parse("    20 07").unwrap_err()
+        );
+    }
+}
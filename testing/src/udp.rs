@@ -0,0 +1,100 @@
+//! A UDP client for testing datagram servers, which can simulate packet loss and reordering.
+
+use crate::rng::{Rng, SEED_VARIABLE};
+use crate::u8s_to_hex_str;
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Larger than any datagram the servers are expected to send.
+const RECEIVE_BUFFER_SIZE: usize = 65_536;
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+pub struct UdpTestClient {
+    socket: UdpSocket,
+    loss: f64,
+    rng: Rng,
+}
+impl UdpTestClient {
+    /// Bind to an OS-assigned loopback port, sending to (and only receiving from) the server on
+    /// `port`.
+    pub fn connect(port: u16) -> Self {
+        let socket =
+            UdpSocket::bind(("127.0.0.1", 0)).expect("Could not bind UDP integration client.");
+        socket
+            .connect(("127.0.0.1", port))
+            .expect("Could not connect to integration server.");
+        socket
+            .set_nonblocking(true)
+            .expect("Could not set socket to non-blocking.");
+        let seed = Rng::seed_from_env();
+        println!("UDP client: using seed {SEED_VARIABLE}={seed}.");
+        Self {
+            socket,
+            loss: 0.0,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Silently drop each datagram sent with the given probability (between 0.0 and 1.0).
+    pub fn with_loss(mut self, probability: f64) -> Self {
+        self.loss = probability;
+        self
+    }
+
+    /// Returns whether the datagram was actually sent (rather than simulated as lost).
+    pub fn send(&mut self, payload: &[u8]) -> bool {
+        if self.rng.chance(self.loss) {
+            return false;
+        }
+        self.socket
+            .send(payload)
+            .expect("Could not send datagram to integration server.");
+        true
+    }
+
+    /// Send datagrams in a random order, returning those actually sent in the order they were.
+    pub fn send_reordered<'a>(&mut self, datagrams: &[&'a [u8]]) -> Vec<&'a [u8]> {
+        let mut datagrams = datagrams.to_vec();
+        self.rng.shuffle(&mut datagrams);
+        datagrams.retain(|datagram| self.send(datagram));
+        datagrams
+    }
+
+    pub fn receive(&self, timeout: Duration) -> Option<Vec<u8>> {
+        let mut buffer = vec![0u8; RECEIVE_BUFFER_SIZE];
+        let now = Instant::now();
+        loop {
+            match self.socket.recv(&mut buffer) {
+                Ok(n) => return Some(buffer[..n].to_vec()),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => (),
+                Err(e) => panic!("Client socket errored: {e:?}"),
+            }
+            if now.elapsed() > timeout {
+                return None;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    pub fn expect_datagram(&self, expected: &[u8], timeout: Duration) {
+        match self.receive(timeout) {
+            Some(datagram) => assert_eq!(
+                u8s_to_hex_str(expected),
+                u8s_to_hex_str(&datagram),
+                "Unexpected datagram received."
+            ),
+            None => panic!("Timeout reached waiting for expected datagram."),
+        }
+    }
+
+    pub fn expect_no_reply(&self, within: Duration) {
+        if let Some(datagram) = self.receive(within) {
+            panic!(
+                "Unexpected datagram received: {}",
+                u8s_to_hex_str(&datagram)
+            );
+        }
+    }
+}
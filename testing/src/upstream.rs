@@ -0,0 +1,24 @@
+//! A local stand-in for the official budget chat server (`chat.protohackers.com`), so that Mob in
+//! the Middle can be tested offline. It is the Budget Chat solution from this repository.
+
+use common::ServerHandle;
+
+pub struct MockChat {
+    server: ServerHandle,
+}
+impl MockChat {
+    pub fn spawn() -> Self {
+        Self {
+            server: chat::spawn_for_test(),
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.server.port
+    }
+
+    /// Address for the proxy to connect to.
+    pub fn address(&self) -> String {
+        format!("127.0.0.1:{}", self.server.port)
+    }
+}